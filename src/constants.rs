@@ -1,9 +1,57 @@
 use near_sdk::{Duration, Gas};
 
 pub const ONE_MINUTE_NANOS: Duration = 60_000_000_000;
+pub const ONE_DAY_NANOS: Duration = 24 * 60 * ONE_MINUTE_NANOS;
 
-// 250Tgas is for MPC sign, 5Tgas for basic fn operations and 5Tgas for promise creation
-pub const MIN_GAS_FOR_GET_SIGNATURE: Gas = Gas::from_tgas(260);
+// Time to live of a request when none is provided
+pub const DEFAULT_REQUEST_TTL: Duration = ONE_DAY_NANOS;
+// Bounds for the request time to live, the owner can change them later
+pub const DEFAULT_MIN_REQUEST_TTL: Duration = ONE_MINUTE_NANOS;
+pub const DEFAULT_MAX_REQUEST_TTL: Duration = 30 * ONE_DAY_NANOS;
+// Upper bound for the owner-configured max, keeps the request deadline from overflowing
+pub const MAX_REQUEST_TTL_LIMIT: Duration = 365 * ONE_DAY_NANOS;
 
+// Amount of requests returned by list views when no limit is provided
+pub const DEFAULT_PAGE_LIMIT: u64 = 50;
+// Amount of expired requests removed by a single cleanup call when no limit is provided
+pub const DEFAULT_CLEANUP_LIMIT: u64 = 20;
+// Amount of legacy request ids examined by a single migrate_requests() call when no limit is provided
+pub const DEFAULT_MIGRATION_LIMIT: u64 = 20;
+// Amount of produced signatures kept per request, the oldest ones are dropped first
+pub const MAX_SIGNATURE_HISTORY: u32 = 10;
+
+// Amount of distinct chains a request can be signed for, keeps the stored counters bounded
+pub const MAX_SIGNED_CHAINS: usize = 32;
+
+// How far the nonce provided by the executor can be ahead of the highest signed one
+pub const MAX_NONCE_GAP: u64 = 1_000;
+// Version of the root key MPC Contract returns from public_key(), which refresh_mpc_public_key() caches
+pub const MPC_PUBLIC_KEY_VERSION: u32 = 0;
+// Bytes the runtime charges for every stored key-value pair on top of their length
+pub const STORAGE_RECORD_OVERHEAD: u64 = 40;
+
+// Gas limit of EVM transaction when executor doesn't provide one
+pub const DEFAULT_TX_GAS: u128 = 21_000;
+
+// 250Tgas is for MPC sign, 5Tgas for basic fn operations, 5Tgas for promise creation
+// and 10Tgas for on_get_signature callback
+pub const MIN_GAS_FOR_GET_SIGNATURE: Gas = Gas::from_tgas(270);
+
+// 5Tgas for MPC experimental_signature_deposit, 5Tgas for callback operations and 5Tgas for promise creation
+pub const GAS_FOR_SIGNATURE_DEPOSIT_QUERY: Gas = Gas::from_tgas(15);
+
+// 10Tgas for DAO get_policy, 5Tgas for callback operations and 5Tgas for promise creation
+// Together with the deposit query it exceeds 300Tgas, so DAO roles need a configured deposit
+pub const GAS_FOR_DAO_AUTHORIZATION: Gas = Gas::from_tgas(20);
+
+pub const GAS_FOR_DAO_POLICY: Gas = Gas::from_tgas(10);
+pub const GAS_FOR_MPC_PUBLIC_KEY: Gas = Gas::from_tgas(10);
+pub const GAS_FOR_MPC_SIGNATURE_DEPOSIT: Gas = Gas::from_tgas(5);
+pub const GAS_FOR_DAO_PROPOSAL: Gas = Gas::from_tgas(10);
+pub const GAS_FOR_ON_DAO_PROPOSAL: Gas = Gas::from_tgas(30);
 pub const GAS_FOR_PROMISE: Gas = Gas::from_tgas(5);
+// Callbacks that only check the promise result and update a few fields
 pub const GAS_FOR_CALLBACK: Gas = Gas::from_tgas(5);
+// Signature validation, RLP encoding, sender recovery via ecrecover host function,
+// nonce and history writes and the event
+pub const GAS_FOR_ON_GET_SIGNATURE: Gas = Gas::from_tgas(10);
@@ -0,0 +1,174 @@
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+
+// Subset of Sputnik DAO v2 types that is required to authorize executors
+// https://github.com/near-daos/sputnik-dao-contract/blob/main/sputnikdao2/src/policy.rs
+// Unknown fields are ignored during deserialization
+
+#[near_sdk::near(serializers = [json])]
+pub enum RoleKind {
+    // Matches everyone
+    Everyone,
+    // Member greater or equal than given balance, requires staking
+    Member(U128),
+    // Set of accounts
+    Group(Vec<AccountId>),
+}
+
+#[near_sdk::near(serializers = [json])]
+pub struct RolePermission {
+    pub name: String,
+    pub kind: RoleKind,
+}
+
+#[derive(PartialEq, Debug)]
+#[near_sdk::near(serializers = [json])]
+pub enum ProposalStatus {
+    InProgress,
+    Approved,
+    Rejected,
+    Removed,
+    Expired,
+    Moved,
+    Failed,
+}
+
+#[near_sdk::near(serializers = [json])]
+pub struct Proposal {
+    pub id: u64,
+    pub description: String,
+    pub status: ProposalStatus,
+}
+
+impl Proposal {
+    // Description must include the commitment to make sure it was approved for the exact request
+    pub fn is_approved_with_commitment(&self, commitment: &str) -> bool {
+        self.status == ProposalStatus::Approved && self.description.contains(commitment)
+    }
+}
+
+#[near_sdk::near(serializers = [json])]
+pub struct Policy {
+    pub roles: Vec<RolePermission>,
+}
+
+impl Policy {
+    pub fn is_member_of_role(&self, account_id: &AccountId, role: &str) -> bool {
+        self.roles
+            .iter()
+            .filter(|role_permission| role_permission.name == role)
+            .any(|role_permission| match &role_permission.kind {
+                RoleKind::Everyone => true,
+                // token weighted membership can't be verified without checking the staking contract
+                RoleKind::Member(_) => false,
+                RoleKind::Group(accounts) => accounts.contains(account_id),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use near_sdk::serde_json::{self, json};
+
+    use super::*;
+
+    fn policy() -> Policy {
+        serde_json::from_value(json!({
+            "roles": [
+                {
+                    "name": "all",
+                    "kind": "Everyone",
+                    "permissions": ["*:AddProposal"],
+                    "vote_policy": {}
+                },
+                {
+                    "name": "council",
+                    "kind": {
+                        "Group": ["council.near", "other-council.near"]
+                    },
+                    "permissions": ["*:*"],
+                    "vote_policy": {}
+                },
+                {
+                    "name": "stakers",
+                    "kind": {
+                        "Member": "1000"
+                    },
+                    "permissions": ["*:VoteApprove"],
+                    "vote_policy": {}
+                }
+            ],
+            "default_vote_policy": {
+                "weight_kind": "RoleWeight",
+                "quorum": "0",
+                "threshold": [1, 2]
+            },
+            "proposal_bond": "100000000000000000000000",
+            "proposal_period": "604800000000000",
+            "bounty_bond": "100000000000000000000000",
+            "bounty_forgiveness_period": "86400000000000"
+        }))
+        .unwrap()
+    }
+
+    fn proposal(status: &str) -> Proposal {
+        serde_json::from_value(json!({
+            "id": 5,
+            "proposer": "council.near",
+            "description": "Sign transfer on all chains\ncommitment: 0xabcdef",
+            "kind": "Vote",
+            "status": status,
+            "vote_counts": {},
+            "votes": {},
+            "submission_time": "1728986555728267025"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_proposal_is_approved_with_commitment() {
+        assert!(proposal("Approved").is_approved_with_commitment("0xabcdef"));
+    }
+
+    #[test]
+    fn test_proposal_is_not_approved_with_other_commitment() {
+        assert!(!proposal("Approved").is_approved_with_commitment("0x123456"));
+    }
+
+    #[test]
+    fn test_proposal_in_progress_is_not_approved() {
+        assert!(!proposal("InProgress").is_approved_with_commitment("0xabcdef"));
+    }
+
+    #[test]
+    fn test_is_member_of_group_role() {
+        let policy = policy();
+
+        let council = AccountId::from_str("council.near").unwrap();
+        let stranger = AccountId::from_str("stranger.near").unwrap();
+
+        assert!(policy.is_member_of_role(&council, "council"));
+        assert!(!policy.is_member_of_role(&stranger, "council"));
+    }
+
+    #[test]
+    fn test_is_member_of_everyone_role() {
+        let policy = policy();
+
+        let stranger = AccountId::from_str("stranger.near").unwrap();
+
+        assert!(policy.is_member_of_role(&stranger, "all"));
+    }
+
+    #[test]
+    fn test_is_not_member_of_token_weighted_or_unknown_role() {
+        let policy = policy();
+
+        let council = AccountId::from_str("council.near").unwrap();
+
+        assert!(!policy.is_member_of_role(&council, "stakers"));
+        assert!(!policy.is_member_of_role(&council, "unknown"));
+    }
+}
@@ -0,0 +1,149 @@
+use ethers_core::k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use ethers_core::k256::elliptic_curve::PrimeField;
+use ethers_core::k256::{AffinePoint, EncodedPoint, ProjectivePoint, Scalar};
+use ethers_core::types::H160;
+use ethers_core::utils::keccak256;
+use near_sdk::{AccountId, CurveType, PublicKey};
+use sha3::{Digest, Sha3_256};
+
+use crate::errors::{ContractError, UnwrapOrPanic};
+
+// Key derivation used by MPC Contract
+// https://github.com/near/mpc/blob/develop/chain-signatures/crypto-shared/src/kdf.rs
+const EPSILON_DERIVATION_PREFIX: &str = "near-mpc-recovery v0.1.0 epsilon derivation:";
+
+pub fn derive_epsilon(predecessor_id: &AccountId, path: &str) -> Scalar {
+    let derivation_path = format!("{EPSILON_DERIVATION_PREFIX}{},{}", predecessor_id, path);
+
+    let hash: [u8; 32] = Sha3_256::digest(derivation_path).into();
+
+    Option::from(Scalar::from_repr(hash.into())).unwrap_or_panic(ContractError::InvalidEpsilon)
+}
+
+pub fn parse_mpc_public_key(public_key: &PublicKey) -> Option<AffinePoint> {
+    if public_key.curve_type() != CurveType::SECP256K1 {
+        return None;
+    }
+
+    // first byte is the curve type, the rest is uncompressed point without 0x04 prefix
+    let mut sec1_bytes = vec![0x04];
+    sec1_bytes.extend_from_slice(&public_key.as_bytes()[1..]);
+
+    let encoded_point = EncodedPoint::from_bytes(sec1_bytes).ok()?;
+
+    Option::from(AffinePoint::from_encoded_point(&encoded_point))
+}
+
+pub fn derive_public_key(mpc_public_key: &AffinePoint, epsilon: Scalar) -> AffinePoint {
+    (ProjectivePoint::GENERATOR * epsilon + mpc_public_key).to_affine()
+}
+
+pub fn to_near_public_key(point: &AffinePoint) -> PublicKey {
+    let encoded_point = point.to_encoded_point(false);
+
+    PublicKey::from_parts(CurveType::SECP256K1, encoded_point.as_bytes()[1..].to_vec())
+        .unwrap_or_panic(ContractError::Unexpected)
+}
+
+pub fn to_evm_address(point: &AffinePoint) -> H160 {
+    let encoded_point = point.to_encoded_point(false);
+    let hash = keccak256(&encoded_point.as_bytes()[1..]);
+
+    H160::from_slice(&hash[12..])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers_core::k256::elliptic_curve::point::AffineCoordinates;
+    use ethers_core::types::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_to_evm_address() {
+        // address of the private key equal to 1
+        let point = ProjectivePoint::GENERATOR.to_affine();
+
+        assert_eq!(
+            to_evm_address(&point),
+            H160::from_str("0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_derive_public_key_matches_derived_private_key() {
+        let epsilon = derive_epsilon(
+            &AccountId::from_str("abstract-dao.near").unwrap(),
+            "dao.near-0",
+        );
+
+        let mpc_private_key = Scalar::from(42u64);
+        let mpc_public_key = (ProjectivePoint::GENERATOR * mpc_private_key).to_affine();
+
+        let derived_public_key = derive_public_key(&mpc_public_key, epsilon);
+
+        assert_eq!(
+            derived_public_key,
+            (ProjectivePoint::GENERATOR * (mpc_private_key + epsilon)).to_affine()
+        );
+    }
+
+    #[test]
+    fn test_derive_evm_address_known_vector() {
+        // root key of MPC Contract on testnet, the expected keys are derived as kdf.rs of MPC does
+        let mpc_public_key = parse_mpc_public_key(&PublicKey::from_str("secp256k1:4NfTiv3UsGahebgTaHyD9vF8KYKMBnfd6kh94mK6xv8fGBiJB8TBtFMP5WWXz6B89Ac1fbpzPwAvoyQebemHFwx3").unwrap()).unwrap();
+
+        let epsilon = derive_epsilon(
+            &AccountId::from_str("abstract-dao.testnet").unwrap(),
+            "dao.testnet-0",
+        );
+        let derived_public_key = derive_public_key(&mpc_public_key, epsilon);
+
+        assert_eq!(
+            to_near_public_key(&derived_public_key),
+            PublicKey::from_str("secp256k1:3CoYxyK2oh6AkJ7nyYVoHuV4sAhqD8jRx2HNsfSXMS3fbtCBCYnabzSGNMXPAiCGwoeLm3sffhgrZF7N7BGyDPux").unwrap()
+        );
+        assert_eq!(
+            to_evm_address(&derived_public_key),
+            H160::from_str("0x765c35aabbb30a65a70a2b14adec381d48423bd8").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_derive_epsilon_depends_on_predecessor_and_path() {
+        let predecessor = AccountId::from_str("abstract-dao.near").unwrap();
+        let other_predecessor = AccountId::from_str("other.near").unwrap();
+
+        assert_ne!(
+            derive_epsilon(&predecessor, "dao.near-0"),
+            derive_epsilon(&predecessor, "dao.near-1")
+        );
+        assert_ne!(
+            derive_epsilon(&predecessor, "dao.near-0"),
+            derive_epsilon(&other_predecessor, "dao.near-0")
+        );
+    }
+
+    #[test]
+    fn test_near_public_key_roundtrip() {
+        let point = (ProjectivePoint::GENERATOR * Scalar::from(7u64)).to_affine();
+
+        let public_key = to_near_public_key(&point);
+
+        assert_eq!(parse_mpc_public_key(&public_key), Some(point));
+        assert_eq!(
+            Bytes::from(public_key.as_bytes()[1..33].to_vec()),
+            Bytes::from(point.x().to_vec())
+        );
+    }
+
+    #[test]
+    fn test_parse_mpc_public_key_rejects_ed25519() {
+        let public_key =
+            PublicKey::from_str("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp").unwrap();
+
+        assert_eq!(parse_mpc_public_key(&public_key), None);
+    }
+}
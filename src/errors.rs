@@ -0,0 +1,191 @@
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use near_sdk::{env, FunctionError};
+
+// Declares the errors together with their codes, so a code can be parsed back into the error
+macro_rules! contract_errors {
+    ($($variant:ident => $code:literal,)*) => {
+        /// Errors of the contract, the contract panics with the code of the error
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        pub enum ContractError {
+            $($variant,)*
+        }
+
+        impl ContractError {
+            // Codes are stable, so relayers can match on them
+            pub fn code(&self) -> &'static str {
+                match self {
+                    $(ContractError::$variant => $code,)*
+                }
+            }
+
+            pub fn from_code(code: &str) -> Option<Self> {
+                match code {
+                    $($code => Some(ContractError::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+contract_errors! {
+    // Access and request lifecycle
+    NotInitialized => "ERR_NOT_INITIALIZED",
+    NotOwner => "ERR_NOT_OWNER",
+    NotFound => "ERR_NOT_FOUND",
+    Forbidden => "ERR_FORBIDDEN",
+    TimeIsUp => "ERR_TIME_IS_UP",
+    RequestCancelled => "ERR_REQUEST_CANCELLED",
+    InvalidTtl => "ERR_INVALID_TTL",
+    InvalidTtlLimits => "ERR_INVALID_TTL_LIMITS",
+    NoExecutors => "ERR_NO_EXECUTORS",
+    ConflictingExecutors => "ERR_CONFLICTING_EXECUTORS",
+    ExecutorsNotListed => "ERR_EXECUTORS_NOT_LISTED",
+    AlreadyExecutor => "ERR_ALREADY_EXECUTOR",
+    NotExecutor => "ERR_NOT_EXECUTOR",
+
+    // Signing restrictions of the request
+    NoChainIds => "ERR_NO_CHAIN_IDS",
+    ChainNotAllowed => "ERR_CHAIN_NOT_ALLOWED",
+    InvalidUsagePolicy => "ERR_INVALID_USAGE_POLICY",
+    AlreadySignedForChain => "ERR_ALREADY_SIGNED_FOR_CHAIN",
+    SignatureLimitReached => "ERR_SIGNATURE_LIMIT_REACHED",
+    ChainLimitReached => "ERR_CHAIN_LIMIT_REACHED",
+    MaxFeePerGasExceeded => "ERR_MAX_FEE_PER_GAS_EXCEEDED",
+    MaxPriorityFeePerGasExceeded => "ERR_MAX_PRIORITY_FEE_PER_GAS_EXCEEDED",
+    GasExceeded => "ERR_GAS_EXCEEDED",
+    AccessListNotSupported => "ERR_ACCESS_LIST_NOT_SUPPORTED",
+    AccessListNotAllowed => "ERR_ACCESS_LIST_NOT_ALLOWED",
+    NonceOverrideNotAllowed => "ERR_NONCE_OVERRIDE_NOT_ALLOWED",
+    NonceBelowBase => "ERR_NONCE_BELOW_BASE",
+    NonceTooHigh => "ERR_NONCE_TOO_HIGH",
+    StaleNonce => "ERR_STALE_NONCE",
+
+    // Attached resources and cross-contract calls
+    InsufficientDeposit => "ERR_INSUFFICIENT_DEPOSIT",
+    InsufficientGas => "ERR_INSUFFICIENT_GAS",
+    TooManyResults => "ERR_TOO_MANY_RESULTS",
+    ProposalNotApproved => "ERR_PROPOSAL_NOT_APPROVED",
+    ProposalAlreadyUsed => "ERR_PROPOSAL_ALREADY_USED",
+    InvalidMpcPublicKey => "ERR_INVALID_MPC_PUBLIC_KEY",
+    MpcSignatureDepositRequired => "ERR_MPC_SIGNATURE_DEPOSIT_REQUIRED",
+
+    // Transaction payload
+    InvalidDeployment => "ERR_INVALID_DEPLOYMENT",
+    InvalidFunctionArguments => "ERR_INVALID_FUNCTION_ARGUMENTS",
+    InvalidConstructorArguments => "ERR_INVALID_CONSTRUCTOR_ARGUMENTS",
+    CantParseAddress => "ERR_CANT_PARSE_ADDRESS",
+    CantParseData => "ERR_CANT_PARSE_DATA",
+    CantParseBytecode => "ERR_CANT_PARSE_BYTECODE",
+    CantParseAccessList => "ERR_CANT_PARSE_ACCESS_LIST",
+    CantParseU256 => "ERR_CANT_PARSE_U256",
+    CantDecodeTx => "ERR_CANT_DECODE_TX",
+
+    // MPC signature
+    // MPC Contract call failed or timed out
+    SignFailed => "ERR_SIGN_FAILED",
+    CantParseSignature => "ERR_CANT_PARSE_SIGNATURE",
+    // big_r isn't a point on secp256k1 curve
+    InvalidBigR => "ERR_INVALID_BIG_R",
+    InvalidS => "ERR_INVALID_S",
+    // s must be in the lower half of the curve order, as required by EIP-2
+    HighS => "ERR_HIGH_S",
+    InvalidRecoveryId => "ERR_INVALID_RECOVERY_ID",
+    CantRecoverSigner => "ERR_CANT_RECOVER_SIGNER",
+    InvalidEpsilon => "ERR_INVALID_EPSILON",
+    InvalidDerivationPath => "ERR_INVALID_DERIVATION_PATH",
+
+    // Invariants that should never be broken
+    StorageDepositCalc => "ERR_STORAGE_DEPOSIT_CALC",
+    Unexpected => "ERR_UNEXPECTED",
+}
+
+// Errors are serialized as their codes, the same way the contract panics with them
+impl Serialize for ContractError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for ContractError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+
+        ContractError::from_code(&code)
+            .ok_or_else(|| de::Error::custom(format!("unknown error code {code}")))
+    }
+}
+
+impl FunctionError for ContractError {
+    fn panic(&self) -> ! {
+        // same as require!, panic_str aborts mocked blockchain outside of wasm
+        if cfg!(target_arch = "wasm32") {
+            env::panic_str(self.code())
+        } else {
+            panic!("{}", self.code())
+        }
+    }
+}
+
+pub fn ensure(condition: bool, error: ContractError) {
+    if !condition {
+        error.panic()
+    }
+}
+
+pub trait UnwrapOrPanic<T> {
+    fn unwrap_or_panic(self, error: ContractError) -> T;
+}
+
+impl<T> UnwrapOrPanic<T> for Option<T> {
+    fn unwrap_or_panic(self, error: ContractError) -> T {
+        self.unwrap_or_else(|| error.panic())
+    }
+}
+
+impl<T, E> UnwrapOrPanic<T> for Result<T, E> {
+    fn unwrap_or_panic(self, error: ContractError) -> T {
+        self.unwrap_or_else(|_| error.panic())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::serde_json;
+
+    use super::*;
+
+    #[test]
+    fn test_error_codes_are_stable() {
+        assert_eq!(ContractError::NotFound.code(), "ERR_NOT_FOUND");
+        assert_eq!(ContractError::TimeIsUp.code(), "ERR_TIME_IS_UP");
+        assert_eq!(
+            ContractError::InvalidFunctionArguments.code(),
+            "ERR_INVALID_FUNCTION_ARGUMENTS"
+        );
+    }
+
+    #[test]
+    fn test_error_json_roundtrip() {
+        let serialized = serde_json::to_string(&ContractError::StaleNonce).unwrap();
+
+        assert_eq!(serialized, "\"ERR_STALE_NONCE\"");
+        assert_eq!(
+            serde_json::from_str::<ContractError>(&serialized).unwrap(),
+            ContractError::StaleNonce
+        );
+    }
+
+    #[test]
+    fn test_error_json_rejects_unknown_code() {
+        assert!(serde_json::from_str::<ContractError>("\"StaleNonce\"").is_err());
+    }
+
+    #[test]
+    #[should_panic = "ERR_CANT_PARSE_ADDRESS"]
+    fn test_unwrap_or_panic_panics_with_code() {
+        let address: Option<u8> = None;
+
+        address.unwrap_or_panic(ContractError::CantParseAddress);
+    }
+}
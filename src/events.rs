@@ -0,0 +1,122 @@
+use near_sdk::{near, AccountId, NearToken, Timestamp};
+
+use crate::primitives::{JsonU256, Request, RequestId, SignatureFailure};
+
+// NEP-297 events, logged as EVENT_JSON:{"standard":"abstract_dao","version":...,"event":...,"data":...}
+// Every change of a request and its signatures is reported, so the request history can be
+// rebuilt from logs alone. Contract configuration changed by the owner isn't logged
+#[near(event_json(standard = "abstract_dao"))]
+pub enum ContractEvent {
+    #[event_version("1.0.0")]
+    RequestRegistered(RequestRegistered),
+    #[event_version("1.0.0")]
+    SignatureRequested(SignatureRequested),
+    #[event_version("1.0.0")]
+    SignatureProduced(SignatureProduced),
+    #[event_version("1.0.0")]
+    SignatureFailed(SignatureFailure),
+    #[event_version("1.0.0")]
+    ExecutorAdded(ExecutorAdded),
+    #[event_version("1.0.0")]
+    ExecutorRemoved(ExecutorRemoved),
+    #[event_version("1.0.0")]
+    RequestCancelled(RequestRemoved),
+    #[event_version("1.0.0")]
+    RequestExpiredCleanup(RequestRemoved),
+}
+
+#[near(serializers = [json])]
+pub struct RequestRegistered {
+    pub request_id: RequestId,
+    pub derivation_path: String,
+    pub created_by: AccountId,
+    pub deadline: Timestamp,
+    // Deposit paid for the storage occupied by the request
+    pub storage_deposit: NearToken,
+}
+
+impl RequestRegistered {
+    pub fn new(request: &Request, storage_deposit: NearToken) -> Self {
+        Self {
+            request_id: request.id,
+            derivation_path: request.derivation_path.clone(),
+            created_by: request.created_by.clone(),
+            deadline: request.deadline,
+            storage_deposit,
+        }
+    }
+}
+
+#[near(serializers = [json])]
+pub struct SignatureRequested {
+    pub request_id: RequestId,
+    pub chain_id: u64,
+    pub derivation_path: String,
+    pub executor_id: AccountId,
+    pub nonce: JsonU256,
+}
+
+#[near(serializers = [json])]
+pub struct SignatureProduced {
+    pub request_id: RequestId,
+    pub chain_id: u64,
+    pub derivation_path: String,
+    pub executor_id: AccountId,
+    // Hex encoded hash of the signed transaction
+    pub tx_hash: String,
+}
+
+#[near(serializers = [json])]
+pub struct ExecutorAdded {
+    pub request_id: RequestId,
+    pub executor_id: AccountId,
+    // Deposit paid for the storage occupied by the executor
+    pub storage_deposit: NearToken,
+}
+
+#[near(serializers = [json])]
+pub struct ExecutorRemoved {
+    pub request_id: RequestId,
+    pub executor_id: AccountId,
+    // Storage deposit returned to the account that paid for the request storage
+    pub refunded_deposit: NearToken,
+    pub refunded_to: AccountId,
+    // Deposit for added executors returned to the creator
+    pub executors_refunded_deposit: NearToken,
+}
+
+#[near(serializers = [json])]
+pub struct RequestRemoved {
+    pub request_id: RequestId,
+    pub derivation_path: String,
+    pub created_by: AccountId,
+    // Storage deposit returned to the account that paid it
+    pub refunded_deposit: NearToken,
+    pub refunded_to: AccountId,
+    // Deposit for added executors returned to the creator
+    pub executors_refunded_deposit: NearToken,
+    // Storage deposit of the signature history returned to the executors that paid it
+    pub history_refunded_deposit: NearToken,
+}
+
+impl RequestRemoved {
+    pub fn new(
+        request: &Request,
+        refunded_deposit: NearToken,
+        history_refunds: &[(AccountId, NearToken)],
+    ) -> Self {
+        Self {
+            request_id: request.id,
+            derivation_path: request.derivation_path.clone(),
+            created_by: request.created_by.clone(),
+            refunded_deposit,
+            refunded_to: request.storage_payer_id.clone(),
+            executors_refunded_deposit: request.executors_storage_deposit,
+            history_refunded_deposit: history_refunds
+                .iter()
+                .fold(NearToken::from_yoctonear(0), |total, (_, refund)| {
+                    total.saturating_add(*refund)
+                }),
+        }
+    }
+}
@@ -1,43 +1,129 @@
-use ethers_core::types::transaction::eip2930::AccessList;
-use ethers_core::types::{Bytes, Eip1559TransactionRequest};
+use std::collections::BTreeMap;
+
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::transaction::eip2930::Eip2930TransactionRequest;
+use ethers_core::types::{Bytes, Eip1559TransactionRequest, TransactionRequest};
 use ethers_core::utils::keccak256;
+use ethers_core::utils::rlp::Rlp;
 use near_sdk::serde_json::json;
-use near_sdk::{env, require, AccountId, Gas, NearToken, Promise, StorageUsage};
-
-use crate::constants::{GAS_FOR_CALLBACK, GAS_FOR_PROMISE};
-use crate::primitives::{BaseEip1559TransactionPayload, OtherEip1559TransactionPayload, Request};
+use near_sdk::store::{IterableSet, LookupMap};
+use near_sdk::{env, AccountId, CryptoHash, Gas, NearToken, Promise, StorageUsage};
+
+use crate::constants::{
+    GAS_FOR_CALLBACK, GAS_FOR_DAO_POLICY, GAS_FOR_DAO_PROPOSAL, GAS_FOR_MPC_PUBLIC_KEY,
+    GAS_FOR_MPC_SIGNATURE_DEPOSIT, GAS_FOR_ON_DAO_PROPOSAL, GAS_FOR_ON_GET_SIGNATURE,
+    GAS_FOR_PROMISE,
+};
+use crate::errors::{ensure, ContractError, UnwrapOrPanic};
+use crate::primitives::{
+    AccessListEntry, BaseEip1559TransactionPayload, DaoProposal, InputRequest,
+    OtherEip1559TransactionPayload, Request, RequestId, StorageKey, TransactionType,
+};
 use crate::Contract;
 
-pub fn create_derivation_path(seed_number: u32) -> String {
-    format!("{}-{}", env::predecessor_account_id(), seed_number)
+pub fn create_derivation_path(account_id: &AccountId, seed_number: u32) -> String {
+    format!("{}-{}", account_id, seed_number)
+}
+
+// Derivation path has "{account_id}-{seed_number}" format
+pub fn is_derivation_path_of(account_id: &AccountId, derivation_path: &str) -> bool {
+    derivation_path
+        .rsplit_once('-')
+        .map_or(false, |(path_account_id, seed_number)| {
+            path_account_id == account_id.as_str() && seed_number.parse::<u32>().is_ok()
+        })
 }
 
 pub fn refund_unused_deposit(used_deposit: NearToken) {
-    let refund = env::attached_deposit().checked_sub(used_deposit).unwrap();
+    let refund = env::attached_deposit()
+        .checked_sub(used_deposit)
+        .unwrap_or_panic(ContractError::InsufficientDeposit);
 
     if refund > NearToken::from_yoctonear(1) {
         Promise::new(env::predecessor_account_id()).transfer(refund);
     }
 }
 
+// Transfers the refunds, the ones going to the same account are sent as one transfer
+pub fn transfer_refunds(refunds: impl IntoIterator<Item = (AccountId, NearToken)>) {
+    let mut merged: BTreeMap<AccountId, NearToken> = BTreeMap::new();
+
+    for (account_id, refund) in refunds {
+        let total = merged
+            .entry(account_id)
+            .or_insert(NearToken::from_yoctonear(0));
+        *total = total.saturating_add(refund);
+    }
+
+    for (account_id, refund) in merged {
+        if !refund.is_zero() {
+            Promise::new(account_id).transfer(refund);
+        }
+    }
+}
+
 pub fn assert_deposit(min_deposit: NearToken) {
-    require!(
+    ensure(
         env::attached_deposit() >= min_deposit,
-        format!(
-            "Deposited amount must be bigger than {} yocto",
-            min_deposit.as_yoctonear()
-        )
+        ContractError::InsufficientDeposit,
+    );
+}
+
+pub fn assert_owner(owner_id: &AccountId) {
+    ensure(
+        env::predecessor_account_id() == *owner_id,
+        ContractError::NotOwner,
     );
 }
 
 pub fn assert_gas(min_gas: Gas) {
-    require!(env::prepaid_gas() >= min_gas, "ERR_INSUFFICIENT_GAS");
+    ensure(
+        env::prepaid_gas() >= min_gas,
+        ContractError::InsufficientGas,
+    );
 }
 
 pub fn calculate_deposit_for_used_storage(used_storage: StorageUsage) -> NearToken {
     env::storage_byte_cost()
         .checked_mul(used_storage as u128)
-        .expect("ERR_STORAGE_DEPOSIT_CALC")
+        .unwrap_or_panic(ContractError::StorageDepositCalc)
+}
+
+pub fn add_to_account_index(
+    index: &mut LookupMap<AccountId, IterableSet<RequestId>>,
+    account_id: AccountId,
+    request_id: RequestId,
+    inner_key: fn(CryptoHash) -> StorageKey,
+) {
+    let account_id_hash = env::sha256_array(account_id.as_bytes());
+    let request_ids = index
+        .entry(account_id)
+        .or_insert_with(|| IterableSet::new(inner_key(account_id_hash)));
+
+    request_ids.insert(request_id);
+    request_ids.flush();
+
+    index.flush();
+}
+
+pub fn remove_from_account_index(
+    index: &mut LookupMap<AccountId, IterableSet<RequestId>>,
+    account_id: &AccountId,
+    request_id: RequestId,
+) {
+    let Some(request_ids) = index.get_mut(account_id) else {
+        return;
+    };
+
+    request_ids.remove(&request_id);
+
+    if request_ids.is_empty() {
+        index.remove(account_id);
+    } else {
+        request_ids.flush();
+    }
+
+    index.flush();
 }
 
 pub fn create_eip1559_tx(
@@ -52,35 +138,95 @@ pub fn create_eip1559_tx(
         to: base_tx.to,
         data: base_tx.data,
         value: base_tx.value,
-        nonce: base_tx.nonce,
         // Other Tx provided by requestor
+        nonce: other_tx.nonce.or(base_tx.nonce),
         chain_id: other_tx.chain_id,
         gas: other_tx.gas,
         max_fee_per_gas: other_tx.max_fee_per_gas,
         max_priority_fee_per_gas: other_tx.max_priority_fee_per_gas,
+        // Fixed by the creator or provided by requestor
+        access_list: if base_tx.access_list.0.is_empty() {
+            other_tx.access_list
+        } else {
+            base_tx.access_list
+        },
         // Unused
         from: None,
-        access_list: AccessList::default(),
     }
 }
 
-pub fn tx_to_vec(tx: Eip1559TransactionRequest) -> Vec<u8> {
-    // byte "2" stands for EIP-1559 Type
-    let mut vec = vec![2u8];
-    vec.extend(tx.rlp().to_vec());
+// Legacy & EIP-2930 transactions have a single gas price, max_fee_per_gas is used for it
+fn create_legacy_tx(tx: Eip1559TransactionRequest) -> TransactionRequest {
+    TransactionRequest {
+        from: None,
+        to: tx.to,
+        gas: tx.gas,
+        gas_price: tx.max_fee_per_gas,
+        value: tx.value,
+        data: tx.data,
+        nonce: tx.nonce,
+        chain_id: tx.chain_id,
+    }
+}
+
+pub fn create_tx(
+    transaction_type: TransactionType,
+    base_payload: BaseEip1559TransactionPayload,
+    other_payload: OtherEip1559TransactionPayload,
+) -> TypedTransaction {
+    let tx = create_eip1559_tx(base_payload, other_payload);
+
+    match transaction_type {
+        TransactionType::Legacy => TypedTransaction::Legacy(create_legacy_tx(tx)),
+        TransactionType::Eip2930 => {
+            let access_list = tx.access_list.clone();
+
+            TypedTransaction::Eip2930(Eip2930TransactionRequest::new(
+                create_legacy_tx(tx),
+                access_list,
+            ))
+        }
+        TransactionType::Eip1559 => TypedTransaction::Eip1559(tx),
+    }
+}
 
-    vec
+pub fn tx_to_vec(tx: TypedTransaction) -> Vec<u8> {
+    // starts with the type byte for typed transactions (1 for EIP-2930, 2 for EIP-1559),
+    // legacy transactions are encoded according to EIP-155
+    tx.rlp().to_vec()
 }
 
-fn build_tx_payload(tx: Eip1559TransactionRequest) -> [u8; 32] {
+pub fn vec_to_tx(vec: &[u8]) -> TypedTransaction {
+    let decoded = match vec.first() {
+        Some(0x01) => Rlp::new(&vec[1..])
+            .as_val::<Eip2930TransactionRequest>()
+            .map(TypedTransaction::Eip2930),
+        Some(0x02) => Rlp::new(&vec[1..])
+            .as_val::<Eip1559TransactionRequest>()
+            .map(TypedTransaction::Eip1559),
+        _ => TransactionRequest::decode_unsigned_rlp(&Rlp::new(vec)).map(TypedTransaction::Legacy),
+    };
+
+    decoded.unwrap_or_panic(ContractError::CantDecodeTx)
+}
+
+// Access list of the transaction in the stored format, None if it's empty
+pub fn tx_access_list(tx: &TypedTransaction) -> Option<Vec<AccessListEntry>> {
+    tx.access_list()
+        .filter(|access_list| !access_list.0.is_empty())
+        .map(|access_list| access_list.0.iter().cloned().map(Into::into).collect())
+}
+
+fn build_tx_payload(tx: TypedTransaction) -> [u8; 32] {
     let vec = tx_to_vec(tx);
     keccak256(vec)
 }
 
 pub fn create_sign_promise(
     account_id: AccountId,
-    tx: Eip1559TransactionRequest,
+    tx: TypedTransaction,
     request: Request,
+    deposit: NearToken,
 ) -> Promise {
     let payload = build_tx_payload(tx.clone());
 
@@ -95,52 +241,195 @@ pub fn create_sign_promise(
     .into_bytes();
 
     let function = "sign".to_owned();
-    let deposit = env::attached_deposit();
     // calculate unused gas
     let gas = env::prepaid_gas()
         .checked_sub(env::used_gas())
-        .unwrap()
+        .unwrap_or_panic(ContractError::InsufficientGas)
         // some Gas will be used to create Promise itself
         .checked_sub(GAS_FOR_PROMISE)
-        .unwrap()
+        .unwrap_or_panic(ContractError::InsufficientGas)
         // some Gas will be allocated for callback
-        .checked_sub(GAS_FOR_CALLBACK)
-        .unwrap();
+        .checked_sub(GAS_FOR_ON_GET_SIGNATURE)
+        .unwrap_or_panic(ContractError::InsufficientGas);
 
     Promise::new(account_id).function_call(function, args, deposit, gas)
 }
 
-pub fn create_on_sign_callback_promise(tx: Eip1559TransactionRequest) -> Promise {
+pub fn create_mpc_public_key_promise(account_id: AccountId) -> Promise {
+    let function = "public_key".to_owned();
+
+    Promise::new(account_id).function_call(
+        function,
+        vec![],
+        NearToken::from_yoctonear(0),
+        GAS_FOR_MPC_PUBLIC_KEY,
+    )
+}
+
+pub fn create_on_mpc_public_key_callback_promise() -> Promise {
+    Contract::ext(env::current_account_id())
+        .with_static_gas(GAS_FOR_CALLBACK)
+        .on_mpc_public_key()
+}
+
+pub fn create_mpc_signature_deposit_promise(account_id: AccountId) -> Promise {
+    let function = "experimental_signature_deposit".to_owned();
+
+    Promise::new(account_id).function_call(
+        function,
+        vec![],
+        NearToken::from_yoctonear(0),
+        GAS_FOR_MPC_SIGNATURE_DEPOSIT,
+    )
+}
+
+// The callback creates sign promise, so it receives all the remaining gas
+pub fn create_on_mpc_signature_deposit_callback_promise(
+    tx: TypedTransaction,
+    request_id: RequestId,
+    derivation_path: String,
+    executor_id: AccountId,
+    deposit: NearToken,
+) -> Promise {
     let vec = tx_to_vec(tx);
 
+    Contract::ext(env::current_account_id()).on_mpc_signature_deposit(
+        Bytes::from(vec).to_string(),
+        request_id,
+        derivation_path,
+        executor_id,
+        deposit,
+    )
+}
+
+pub fn create_dao_policy_promise(dao_id: AccountId) -> Promise {
+    let function = "get_policy".to_owned();
+
+    Promise::new(dao_id).function_call(
+        function,
+        vec![],
+        NearToken::from_yoctonear(0),
+        GAS_FOR_DAO_POLICY,
+    )
+}
+
+pub fn create_on_dao_policy_callback_promise(
+    request_id: RequestId,
+    other_payload: OtherEip1559TransactionPayload,
+    executor_id: AccountId,
+    deposit: NearToken,
+) -> Promise {
+    // calculate unused gas
+    let gas = env::prepaid_gas()
+        .checked_sub(env::used_gas())
+        .unwrap_or_panic(ContractError::InsufficientGas)
+        // some Gas will be allocated for DAO call
+        .checked_sub(GAS_FOR_DAO_POLICY)
+        .unwrap_or_panic(ContractError::InsufficientGas)
+        // some Gas will be used to create Promises
+        .checked_sub(GAS_FOR_PROMISE)
+        .unwrap_or_panic(ContractError::InsufficientGas);
+
     Contract::ext(env::current_account_id())
+        .with_static_gas(gas)
+        .on_dao_policy(request_id, other_payload, executor_id, deposit)
+}
+
+pub fn create_dao_proposal_promise(proposal: &DaoProposal) -> Promise {
+    let args = json!({
+        "id": proposal.proposal_id
+    })
+    .to_string()
+    .into_bytes();
+
+    let function = "get_proposal".to_owned();
+
+    Promise::new(proposal.dao_id.clone()).function_call(
+        function,
+        args,
+        NearToken::from_yoctonear(0),
+        GAS_FOR_DAO_PROPOSAL,
+    )
+}
+
+pub fn create_on_dao_proposal_callback_promise(
+    request: InputRequest,
+    proposal: DaoProposal,
+    payer_id: AccountId,
+    deposit: NearToken,
+) -> Promise {
+    Contract::ext(env::current_account_id())
+        .with_static_gas(GAS_FOR_ON_DAO_PROPOSAL)
+        .on_dao_proposal(request, proposal, payer_id, deposit)
+}
+
+// Returns deposit to the account and fails the receipt chain afterwards,
+// since panicking in a callback would keep the deposit on the contract
+pub fn refund_and_fail(account_id: AccountId, deposit: NearToken, error: ContractError) -> Promise {
+    let fail_promise = Contract::ext(env::current_account_id())
         .with_static_gas(GAS_FOR_CALLBACK)
-        .on_get_signature(Bytes::from(vec.clone()).to_string())
+        .on_refunded_failure(error);
+
+    if deposit.is_zero() {
+        return fail_promise;
+    }
+
+    Promise::new(account_id)
+        .transfer(deposit)
+        .then(fail_promise)
+}
+
+pub fn create_on_sign_callback_promise(
+    tx: TypedTransaction,
+    request_id: RequestId,
+    derivation_path: String,
+    executor_id: AccountId,
+    deposit: NearToken,
+    storage_deposit: NearToken,
+) -> Promise {
+    let vec = tx_to_vec(tx);
+
+    Contract::ext(env::current_account_id())
+        .with_static_gas(GAS_FOR_ON_GET_SIGNATURE)
+        .on_get_signature(
+            Bytes::from(vec.clone()).to_string(),
+            request_id,
+            derivation_path,
+            executor_id,
+            deposit,
+            storage_deposit,
+        )
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
+    use crate::primitives::{AccessListEntry, JsonU256};
     use ethers_core::types::{NameOrAddress, H160, U256, U64};
-    use near_sdk::{
-        json_types::U128, test_utils::VMContextBuilder, testing_env, AccountId, NearToken,
-    };
+    use near_sdk::{test_utils::VMContextBuilder, testing_env, AccountId, NearToken};
 
     use super::*;
 
     #[test]
     fn test_derivation_path_creation() {
-        let mut context = VMContextBuilder::new();
-        context.predecessor_account_id(AccountId::from_str("account").unwrap());
-
-        testing_env!(context.build());
+        let account_id = AccountId::from_str("account").unwrap();
 
-        let derivation_path = create_derivation_path(11111111);
+        let derivation_path = create_derivation_path(&account_id, 11111111);
 
         assert_eq!(derivation_path, "account-11111111");
     }
 
+    #[test]
+    fn test_is_derivation_path_of() {
+        let account_id = AccountId::from_str("user-1.near").unwrap();
+
+        assert!(is_derivation_path_of(&account_id, "user-1.near-0"));
+        assert!(!is_derivation_path_of(&account_id, "user-1.near"));
+        assert!(!is_derivation_path_of(&account_id, "user-1.near-x"));
+        assert!(!is_derivation_path_of(&account_id, "user-2.near-0"));
+    }
+
     #[test]
     fn test_assert_enough_deposit() {
         let mut context = VMContextBuilder::new();
@@ -198,17 +487,20 @@ mod tests {
     #[test]
     fn test_create_eip1559_tx() {
         let base_payload = BaseEip1559TransactionPayload {
-            to: "0x0000000000000000000000000000000000000000".to_string(),
-            nonce: U128(2 * u64::MAX as u128 + 5),
-            value: Some(U128(u64::MAX as u128 - 125)),
+            to: Some("0x0000000000000000000000000000000000000000".to_string()),
+            nonce: JsonU256::from(2 * u64::MAX as u128 + 5),
+            value: Some(JsonU256::from(u64::MAX as u128 - 125)),
             data: Some("0x2386f26fc10000".to_string()),
+            access_list: None,
         };
 
         let other_payload = OtherEip1559TransactionPayload {
             chain_id: 1111,
-            gas: Some(U128(42_000)),
-            max_fee_per_gas: U128(120_000),
-            max_priority_fee_per_gas: U128(120_000),
+            gas: Some(JsonU256::from(42_000)),
+            max_fee_per_gas: JsonU256::from(120_000),
+            max_priority_fee_per_gas: JsonU256::from(120_000),
+            nonce: None,
+            access_list: None,
         };
 
         let tx = create_eip1559_tx(base_payload, other_payload);
@@ -233,20 +525,23 @@ mod tests {
     #[test]
     fn test_build_tx_payload() {
         let base_payload = BaseEip1559TransactionPayload {
-            to: "0x427F9620Be0fe8Db2d840E2b6145D1CF2975bcaD".to_string(),
-            value: Some(U128(1_000_000_000_000_000)),
+            to: Some("0x427F9620Be0fe8Db2d840E2b6145D1CF2975bcaD".to_string()),
+            value: Some(JsonU256::from(1_000_000_000_000_000)),
             data: None,
-            nonce: U128(0),
+            nonce: JsonU256::from(0),
+            access_list: None,
         };
 
         let other_payload = OtherEip1559TransactionPayload {
-            gas: Some(U128(21_000)),
-            max_fee_per_gas: U128(21_814_571_193),
-            max_priority_fee_per_gas: U128(669_340_333),
+            gas: Some(JsonU256::from(21_000)),
+            max_fee_per_gas: JsonU256::from(21_814_571_193),
+            max_priority_fee_per_gas: JsonU256::from(669_340_333),
             chain_id: 11_155_111,
+            nonce: None,
+            access_list: None,
         };
 
-        let tx = create_eip1559_tx(base_payload, other_payload);
+        let tx = create_tx(TransactionType::Eip1559, base_payload, other_payload);
 
         let payload = build_tx_payload(tx);
 
@@ -258,4 +553,172 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_build_legacy_tx_payload() {
+        // signing data example from EIP-155
+        let base_payload = BaseEip1559TransactionPayload {
+            to: Some("0x3535353535353535353535353535353535353535".to_string()),
+            value: Some(JsonU256::from(1_000_000_000_000_000_000)),
+            data: None,
+            nonce: JsonU256::from(9),
+            access_list: None,
+        };
+
+        let other_payload = OtherEip1559TransactionPayload {
+            gas: Some(JsonU256::from(21_000)),
+            max_fee_per_gas: JsonU256::from(20_000_000_000),
+            max_priority_fee_per_gas: JsonU256::from(0),
+            chain_id: 1,
+            nonce: None,
+            access_list: None,
+        };
+
+        let tx = create_tx(TransactionType::Legacy, base_payload, other_payload);
+
+        assert_eq!(
+            Bytes::from(build_tx_payload(tx)).to_string(),
+            "0xdaf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+    }
+
+    #[test]
+    fn test_create_eip2930_tx() {
+        let base_payload = BaseEip1559TransactionPayload {
+            to: Some("0x427F9620Be0fe8Db2d840E2b6145D1CF2975bcaD".to_string()),
+            value: None,
+            data: None,
+            nonce: JsonU256::from(0),
+            access_list: None,
+        };
+
+        let other_payload = OtherEip1559TransactionPayload {
+            gas: None,
+            max_fee_per_gas: JsonU256::from(21_814_571_193),
+            max_priority_fee_per_gas: JsonU256::from(669_340_333),
+            chain_id: 56,
+            nonce: None,
+            access_list: None,
+        };
+
+        let tx = create_tx(TransactionType::Eip2930, base_payload, other_payload);
+
+        assert_eq!(tx.gas_price(), Some(U256::from(21_814_571_193u64)));
+        assert_eq!(tx.chain_id(), Some(U64([56])));
+        // byte "1" stands for EIP-2930 Type
+        assert_eq!(tx_to_vec(tx)[0], 1u8);
+    }
+
+    #[test]
+    fn test_create_eip1559_tx_with_access_list() {
+        let entry = |address: &str| AccessListEntry {
+            address: address.to_string(),
+            storage_keys: vec![],
+        };
+
+        let mut base_payload = BaseEip1559TransactionPayload {
+            to: Some("0x427F9620Be0fe8Db2d840E2b6145D1CF2975bcaD".to_string()),
+            value: None,
+            data: None,
+            nonce: JsonU256::from(0),
+            access_list: None,
+        };
+
+        let other_payload = || OtherEip1559TransactionPayload {
+            gas: None,
+            max_fee_per_gas: JsonU256::from(21_814_571_193),
+            max_priority_fee_per_gas: JsonU256::from(669_340_333),
+            chain_id: 1,
+            nonce: None,
+            access_list: Some(vec![entry("0x0000000000000000000000000000000000000001")]),
+        };
+
+        // provided by requestor
+        let tx = create_eip1559_tx(base_payload.clone(), other_payload());
+        assert_eq!(tx.access_list.0[0].address, H160::from_low_u64_be(1));
+
+        // fixed by the creator
+        base_payload.access_list = Some(vec![entry("0x0000000000000000000000000000000000000002")]);
+        let tx = create_eip1559_tx(base_payload.clone(), other_payload());
+        assert_eq!(tx.access_list.0.len(), 1);
+        assert_eq!(tx.access_list.0[0].address, H160::from_low_u64_be(2));
+
+        // access list is part of the signed payload
+        base_payload.access_list = None;
+        let mut without_access_list = other_payload();
+        without_access_list.access_list = None;
+        assert_ne!(
+            build_tx_payload(create_tx(
+                TransactionType::Eip1559,
+                base_payload.clone(),
+                other_payload()
+            )),
+            build_tx_payload(create_tx(
+                TransactionType::Eip1559,
+                base_payload,
+                without_access_list
+            ))
+        );
+    }
+
+    #[test]
+    fn test_create_eip1559_tx_with_nonce_override() {
+        let base_payload = BaseEip1559TransactionPayload {
+            to: Some("0x427F9620Be0fe8Db2d840E2b6145D1CF2975bcaD".to_string()),
+            value: None,
+            data: None,
+            nonce: JsonU256::from(3),
+            access_list: None,
+        };
+
+        let other_payload = OtherEip1559TransactionPayload {
+            gas: None,
+            max_fee_per_gas: JsonU256::from(21_814_571_193),
+            max_priority_fee_per_gas: JsonU256::from(669_340_333),
+            chain_id: 1,
+            nonce: Some(JsonU256::from(42)),
+            access_list: None,
+        };
+
+        let tx = create_eip1559_tx(base_payload, other_payload);
+
+        assert_eq!(tx.nonce, Some(U256::from(42)));
+    }
+
+    #[test]
+    fn test_vec_to_tx_roundtrip() {
+        let base_payload = BaseEip1559TransactionPayload {
+            to: None,
+            value: Some(JsonU256::from(7)),
+            data: Some("0x2386f26fc10000".to_string()),
+            nonce: JsonU256::from(5),
+            access_list: Some(vec![AccessListEntry {
+                address: "0x0000000000000000000000000000000000000001".to_string(),
+                storage_keys: vec![],
+            }]),
+        };
+
+        let other_payload = || OtherEip1559TransactionPayload {
+            gas: Some(JsonU256::from(42_000)),
+            max_fee_per_gas: JsonU256::from(21_814_571_193),
+            max_priority_fee_per_gas: JsonU256::from(669_340_333),
+            chain_id: 11_155_111,
+            nonce: None,
+            access_list: None,
+        };
+
+        for transaction_type in [
+            TransactionType::Legacy,
+            TransactionType::Eip2930,
+            TransactionType::Eip1559,
+        ] {
+            let tx = create_tx(transaction_type, base_payload.clone(), other_payload());
+            let vec = tx_to_vec(tx.clone());
+
+            let decoded = vec_to_tx(&vec);
+
+            assert_eq!(tx_to_vec(decoded.clone()), vec);
+            assert_eq!(decoded.sighash(), tx.sighash());
+        }
+    }
 }
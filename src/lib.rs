@@ -1,23 +1,59 @@
 mod constants;
+mod dao;
+mod derivation;
+mod errors;
+mod events;
 mod helpers;
+mod migration;
 mod primitives;
+mod signature;
 
-use constants::{MIN_GAS_FOR_GET_SIGNATURE, ONE_MINUTE_NANOS};
+use std::collections::BTreeMap;
+
+use constants::{
+    DEFAULT_CLEANUP_LIMIT, DEFAULT_MAX_REQUEST_TTL, DEFAULT_MIGRATION_LIMIT,
+    DEFAULT_MIN_REQUEST_TTL, DEFAULT_PAGE_LIMIT, DEFAULT_REQUEST_TTL, GAS_FOR_DAO_AUTHORIZATION,
+    GAS_FOR_SIGNATURE_DEPOSIT_QUERY, MAX_NONCE_GAP, MAX_SIGNATURE_HISTORY,
+    MIN_GAS_FOR_GET_SIGNATURE, MPC_PUBLIC_KEY_VERSION,
+};
+use dao::{Policy, Proposal};
+use derivation::{
+    derive_epsilon, derive_public_key, parse_mpc_public_key, to_evm_address, to_near_public_key,
+};
+use errors::{ensure, ContractError, UnwrapOrPanic};
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::Bytes;
+use events::{
+    ContractEvent, ExecutorAdded, ExecutorRemoved, RequestRegistered, RequestRemoved,
+    SignatureProduced, SignatureRequested,
+};
 use helpers::{
-    assert_deposit, assert_gas, calculate_deposit_for_used_storage, create_derivation_path,
-    create_eip1559_tx, create_on_sign_callback_promise, create_sign_promise, refund_unused_deposit,
+    add_to_account_index, assert_deposit, assert_gas, assert_owner,
+    calculate_deposit_for_used_storage, create_dao_policy_promise, create_dao_proposal_promise,
+    create_derivation_path, create_mpc_public_key_promise, create_mpc_signature_deposit_promise,
+    create_on_dao_policy_callback_promise, create_on_dao_proposal_callback_promise,
+    create_on_mpc_public_key_callback_promise, create_on_mpc_signature_deposit_callback_promise,
+    create_on_sign_callback_promise, create_sign_promise, create_tx, is_derivation_path_of,
+    refund_and_fail, refund_unused_deposit, remove_from_account_index, transfer_refunds,
+    tx_access_list, vec_to_tx,
 };
+use migration::{ContractV0, RequestsMigration};
 use near_sdk::serde_json;
 use near_sdk::{
     env::{self, block_timestamp},
-    near, require,
-    store::LookupMap,
-    AccountId, NearToken, PanicOnDefault, Promise, PromiseResult,
+    near,
+    store::{IterableMap, IterableSet, LookupMap, LookupSet},
+    AccountId, Duration, FunctionError, Gas, NearToken, PanicOnDefault, Promise, PromiseOrValue,
+    PromiseResult, PublicKey, StorageUsage,
 };
 use primitives::{
-    GetSignatureResponse, InputRequest, OtherEip1559TransactionPayload,
-    RegisterSignatureReqResponse, Request, RequestId, StorageKey,
+    AllowedExecutors, BaseEip1559TransactionPayload, CleanupExpiredResponse, DaoProposal,
+    DerivedAccount, EvmSignature, GetSignatureResponse, GetSignatureResult, InputRequest, JsonU256,
+    OtherEip1559TransactionPayload, RegisterSignatureReqResponse, Request, RequestId,
+    RequestStatus, RequestTtlLimits, SignatureFailure, SignatureRecord, SignatureRecordKey,
+    SignatureResponse, SignerChainKey, StorageKey, TransactionType,
 };
+use signature::{sign_tx, to_bytes32, MpcSignature};
 
 // Define the contract structure
 #[derive(PanicOnDefault)]
@@ -26,349 +62,4188 @@ pub struct Contract {
     /// Next available id for the requests.
     pub next_request_id: RequestId,
     /// Map of signing requests
-    pub requests: LookupMap<RequestId, Request>,
+    pub requests: IterableMap<RequestId, Request>,
+    /// Ids of the requests registered by an account
+    pub requests_by_creator: LookupMap<AccountId, IterableSet<RequestId>>,
+    /// Ids of the requests an account is allowed to execute
+    pub requests_by_executor: LookupMap<AccountId, IterableSet<RequestId>>,
+    /// Ids of the requests that were cancelled by their creators
+    pub cancelled_requests: LookupSet<RequestId>,
+    /// DAO proposals that were already used to register a request
+    pub used_proposals: LookupSet<DaoProposal>,
+    /// Highest nonce signed for a derived account on a chain
+    pub highest_signed_nonces: LookupMap<SignerChainKey, JsonU256>,
+    /// Latest signatures produced for a request, removed together with the request
+    pub signature_history: LookupMap<SignatureRecordKey, SignatureRecord>,
     /// MPC Account ID
     pub mpc_contract_id: AccountId,
+    /// Cached root public key of MPC Contract, used to derive accounts
+    pub mpc_public_key: Option<PublicKey>,
+    /// Deposit forwarded to MPC Contract sign(), queried from MPC Contract for each call if not set
+    pub mpc_signature_deposit: Option<NearToken>,
+    /// Account that is allowed to change contract settings
+    pub owner_id: AccountId,
+    /// Bounds for the time to live that can be chosen for a request
+    pub request_ttl_limits: RequestTtlLimits,
+    /// Legacy requests that are still to be moved by migrate_requests()
+    pub requests_migration: Option<RequestsMigration>,
 }
 
 // Public API
 #[near]
 impl Contract {
     #[init]
-    pub fn new(mpc_contract_id: AccountId) -> Self {
+    pub fn new(mpc_contract_id: AccountId, owner_id: Option<AccountId>) -> Self {
         Self {
             next_request_id: 0,
-            requests: LookupMap::new(StorageKey::AllRequests),
+            requests: IterableMap::new(StorageKey::Requests),
+            requests_by_creator: LookupMap::new(StorageKey::RequestsByCreator),
+            requests_by_executor: LookupMap::new(StorageKey::RequestsByExecutor),
+            cancelled_requests: LookupSet::new(StorageKey::CancelledRequests),
+            used_proposals: LookupSet::new(StorageKey::UsedProposals),
+            highest_signed_nonces: LookupMap::new(StorageKey::HighestSignedNonces),
+            signature_history: LookupMap::new(StorageKey::SignatureHistory),
             mpc_contract_id: mpc_contract_id.clone(),
+            mpc_public_key: None,
+            mpc_signature_deposit: None,
+            owner_id: owner_id.unwrap_or_else(env::predecessor_account_id),
+            request_ttl_limits: RequestTtlLimits {
+                min: DEFAULT_MIN_REQUEST_TTL,
+                max: DEFAULT_MAX_REQUEST_TTL,
+            },
+            requests_migration: None,
         }
     }
 
-    pub fn get_mpc_contract_id(&self) -> AccountId {
-        self.mpc_contract_id.clone()
-    }
-
-    #[payable]
-    pub fn register_signature_request(
-        &mut self,
-        request: InputRequest,
-    ) -> RegisterSignatureReqResponse {
-        let storage_used_before = env::storage_usage();
-        let new_request = self.add_request(request);
-        let storage_used_after = env::storage_usage();
+    /// Upgrades the state layout, legacy requests are moved afterwards with migrate_requests()
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(owner_id: Option<AccountId>) -> Self {
+        let old_state: ContractV0 =
+            env::state_read().unwrap_or_panic(ContractError::NotInitialized);
 
-        let used_storage = storage_used_after
-            .checked_sub(storage_used_before)
-            .expect("ERR_UNEXPECTED");
+        old_state.migrate(owner_id.unwrap_or_else(env::predecessor_account_id))
+    }
 
-        let storage_deposit = calculate_deposit_for_used_storage(used_storage);
+    /// Moves the next `limit` legacy request ids into the new layout and builds the indexes
+    /// Should be called until get_requests_migration() returns null
+    #[private]
+    pub fn migrate_requests(&mut self, limit: Option<u64>) -> Option<RequestsMigration> {
+        self.migrate_legacy_requests(limit.unwrap_or(DEFAULT_MIGRATION_LIMIT));
 
-        assert_deposit(storage_deposit);
-        refund_unused_deposit(storage_deposit);
+        self.requests_migration
+    }
 
-        RegisterSignatureReqResponse {
-            request_id: new_request.id,
-            deadline: new_request.deadline,
-            derivation_path: new_request.derivation_path,
-            mpc_account_id: self.mpc_contract_id.clone(),
-            allowed_account_id: new_request.allowed_account_id,
-        }
+    pub fn get_requests_migration(&self) -> Option<RequestsMigration> {
+        self.requests_migration
     }
 
-    #[payable]
-    pub fn get_signature(
-        &mut self,
-        request_id: RequestId,
-        other_payload: OtherEip1559TransactionPayload,
-    ) -> Promise {
-        assert_deposit(NearToken::from_yoctonear(1));
-        assert_gas(MIN_GAS_FOR_GET_SIGNATURE);
+    pub fn get_mpc_contract_id(&self) -> AccountId {
+        self.mpc_contract_id.clone()
+    }
 
-        let request = self.get_request_or_panic(request_id);
+    pub fn get_mpc_public_key(&self) -> Option<PublicKey> {
+        self.mpc_public_key.clone()
+    }
 
-        require!(
-            !request.is_time_exceeded(env::block_timestamp()),
-            "ERR_TIME_IS_UP"
-        );
+    /// Fetches MPC Contract public key and caches it to derive accounts
+    pub fn refresh_mpc_public_key(&mut self) -> Promise {
+        create_mpc_public_key_promise(self.mpc_contract_id.clone())
+            .then(create_on_mpc_public_key_callback_promise())
+    }
 
-        require!(
-            request.is_account_allowed(env::predecessor_account_id()),
-            "ERR_FORBIDDEN"
+    #[private]
+    pub fn on_mpc_public_key(&mut self) -> PublicKey {
+        // https://docs.rs/near-sdk/latest/near_sdk/env/fn.promise_results_count.html
+        ensure(
+            env::promise_results_count() == 1,
+            ContractError::TooManyResults,
         );
 
-        let tx = create_eip1559_tx(request.payload.clone(), other_payload);
+        let public_key = match env::promise_result(0) {
+            PromiseResult::Successful(data) => serde_json::from_slice::<PublicKey>(&data).ok(),
+            _ => None,
+        }
+        .filter(|public_key| parse_mpc_public_key(public_key).is_some())
+        .unwrap_or_panic(ContractError::InvalidMpcPublicKey);
 
-        let sign_promise =
-            create_sign_promise(self.mpc_contract_id.clone(), tx.clone(), request.clone());
-        let callback_promise = create_on_sign_callback_promise(tx);
+        self.mpc_public_key = Some(public_key.clone());
 
-        sign_promise.then(callback_promise)
+        public_key
     }
 
-    #[private]
-    pub fn on_get_signature(&mut self, tx_hex: String) -> GetSignatureResponse {
-        // https://docs.rs/near-sdk/latest/near_sdk/env/fn.promise_results_count.html
-        assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
+    /// Account that signs transactions for the requests of the creator with the seed number
+    pub fn get_derived_account(
+        &self,
+        creator_id: AccountId,
+        derivation_seed_number: u32,
+    ) -> Option<DerivedAccount> {
+        self.derive_account(
+            create_derivation_path(&creator_id, derivation_seed_number),
+            MPC_PUBLIC_KEY_VERSION,
+        )
+    }
 
-        let signature_json = match env::promise_result(0) {
-            PromiseResult::Successful(data) => {
-                serde_json::from_slice::<serde_json::Value>(data.as_slice())
-                    .expect("Couldn't deserialize signature!")
-            }
-            _ => env::panic_str("Signature couldn't be decoded from Promise response!"),
-        };
+    pub fn get_owner_id(&self) -> AccountId {
+        self.owner_id.clone()
+    }
 
-        GetSignatureResponse {
-            tx: tx_hex,
-            signature: signature_json,
-        }
+    pub fn get_request_ttl_limits(&self) -> RequestTtlLimits {
+        self.request_ttl_limits.clone()
     }
-}
 
-/// Internal helpers API
-impl Contract {
-    fn add_request(&mut self, input_request: InputRequest) -> Request {
-        let current_request_id = self.next_request_id;
-        self.next_request_id += 1;
+    #[payable]
+    pub fn set_request_ttl_limits(&mut self, limits: RequestTtlLimits) {
+        assert_deposit(NearToken::from_yoctonear(1));
+        assert_owner(&self.owner_id);
 
-        let internal_request = Request {
-            id: current_request_id,
-            allowed_account_id: input_request.allowed_account_id,
-            payload: input_request.transaction_payload.into(),
-            derivation_path: create_derivation_path(input_request.derivation_seed_number),
-            key_version: input_request.key_version.unwrap_or(0),
-            deadline: block_timestamp() + 24 * 60 * ONE_MINUTE_NANOS, // in one day
-        };
-        self.requests
-            .insert(internal_request.id, internal_request.clone());
-        // this is required as LookupMap doesn't write state immediately
-        // Bug4 -> https://docs.near.org/build/smart-contracts/anatomy/collections#error-prone-patterns
-        self.requests.flush();
+        ensure(limits.is_valid(), ContractError::InvalidTtlLimits);
 
-        internal_request
+        self.request_ttl_limits = limits;
     }
 
-    fn get_request_or_panic(&self, request_id: RequestId) -> &Request {
-        // TODO: use errors from Enum
-        self.requests.get(&request_id).expect("ERR_NOT_FOUND")
+    pub fn get_mpc_signature_deposit(&self) -> Option<NearToken> {
+        self.mpc_signature_deposit
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
 
-    use super::*;
-    use ethers_core::{
-        abi::{Function, Param, ParamType, StateMutability, Token},
-        types::U256,
-    };
-    use near_sdk::{json_types::U128, test_utils::VMContextBuilder, testing_env, Gas, NearToken};
-    use primitives::{FunctionData, InputTransactionPayload, OtherEip1559TransactionPayload};
+    /// Fixes the deposit forwarded to MPC Contract, None makes it queried for each signature
+    #[payable]
+    pub fn set_mpc_signature_deposit(&mut self, deposit: Option<NearToken>) {
+        assert_deposit(NearToken::from_yoctonear(1));
+        assert_owner(&self.owner_id);
 
-    fn current() -> AccountId {
-        AccountId::from_str("current").unwrap()
+        self.mpc_signature_deposit = deposit;
     }
 
-    fn user1() -> AccountId {
-        AccountId::from_str("user1").unwrap()
+    pub fn get_highest_signed_nonce(
+        &self,
+        derivation_path: String,
+        chain_id: u64,
+    ) -> Option<JsonU256> {
+        self.highest_signed_nonces
+            .get(&SignerChainKey {
+                derivation_path,
+                chain_id,
+            })
+            .copied()
     }
 
-    fn user2() -> AccountId {
-        AccountId::from_str("user2").unwrap()
-    }
+    /// Forgets the highest signed nonce, e.g. once a transaction with it was dropped from the chain
+    /// Allowed for the contract owner and the account the derivation path belongs to
+    #[payable]
+    pub fn reset_highest_signed_nonce(&mut self, derivation_path: String, chain_id: u64) {
+        assert_deposit(NearToken::from_yoctonear(1));
 
-    fn signer() -> AccountId {
-        AccountId::from_str("signer").unwrap()
+        let predecessor_id = env::predecessor_account_id();
+        ensure(
+            predecessor_id == self.owner_id
+                || is_derivation_path_of(&predecessor_id, &derivation_path),
+            ContractError::Forbidden,
+        );
+
+        self.highest_signed_nonces.remove(&SignerChainKey {
+            derivation_path,
+            chain_id,
+        });
     }
 
-    fn setup() -> (Contract, VMContextBuilder) {
-        let mut context = VMContextBuilder::new();
-        let contract = Contract::new(signer());
+    /// Signatures produced for the request, oldest first, optionally only the ones for the chain
+    pub fn get_signature_history(
+        &self,
+        request_id: RequestId,
+        chain_id: Option<u64>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<SignatureRecord> {
+        let Some(request) = self.requests.get(&request_id) else {
+            return vec![];
+        };
 
-        context.current_account_id(current());
-        context.account_balance(NearToken::from_near(1));
-        context.attached_deposit(NearToken::from_millinear(10));
-        context.predecessor_account_id(user1());
-        context.block_timestamp(0);
-        context.prepaid_gas(Gas::from_tgas(300));
+        let recorded = request.recorded_signature_count;
+        (recorded.saturating_sub(MAX_SIGNATURE_HISTORY)..recorded)
+            .filter_map(|index| {
+                self.signature_history.get(&SignatureRecordKey {
+                    request_id,
+                    slot: index % MAX_SIGNATURE_HISTORY,
+                })
+            })
+            .filter(|record| chain_id.map_or(true, |chain_id| record.chain_id == chain_id))
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
 
-        testing_env!(context.build());
+    pub fn get_request(&self, request_id: RequestId) -> Option<Request> {
+        self.requests.get(&request_id).cloned()
+    }
 
-        (contract, context)
+    pub fn list_requests(
+        &self,
+        status: Option<RequestStatus>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Request> {
+        self.paginate_requests(self.requests.keys(), status, from_index, limit)
     }
 
-    fn input_request() -> InputRequest {
-        InputRequest {
-            allowed_account_id: user1(),
-            derivation_seed_number: 0,
-            transaction_payload: InputTransactionPayload {
-                to: "0x0000000000000000000000000000000000000000".to_string(),
-                function_data: None,
-                value: None,
-                nonce: U128(0),
-            },
-            key_version: None,
+    pub fn list_requests_by_creator(
+        &self,
+        account_id: AccountId,
+        status: Option<RequestStatus>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Request> {
+        match self.requests_by_creator.get(&account_id) {
+            Some(request_ids) => {
+                self.paginate_requests(request_ids.iter(), status, from_index, limit)
+            }
+            None => vec![],
         }
     }
 
-    fn other_payload() -> OtherEip1559TransactionPayload {
-        OtherEip1559TransactionPayload {
-            chain_id: 1,
-            gas: Some(U128(42_000)),
-            max_fee_per_gas: U128(120_000),
-            max_priority_fee_per_gas: U128(120_000),
+    /// Requests that list the account in `allowed_executors`, the ones allowing `Anyone` or
+    /// a DAO role aren't indexed per account and can be found with `list_requests()`
+    pub fn list_requests_for_executor(
+        &self,
+        account_id: AccountId,
+        status: Option<RequestStatus>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Request> {
+        match self.requests_by_executor.get(&account_id) {
+            Some(request_ids) => {
+                self.paginate_requests(request_ids.iter(), status, from_index, limit)
+            }
+            None => vec![],
         }
     }
 
-    #[test]
-    fn test_setup_succeeds() {
-        setup();
-    }
+    #[payable]
+    pub fn register_signature_request(
+        &mut self,
+        request: InputRequest,
+    ) -> RegisterSignatureReqResponse {
+        let creator_id = env::predecessor_account_id();
+        let (new_request, storage_deposit) = self.with_storage_deposit(|contract| {
+            contract.add_request(request, creator_id.clone(), creator_id)
+        });
 
-    #[test]
-    fn test_register_signature_request() {
-        let (mut contract, _) = setup();
+        assert_deposit(storage_deposit);
+        refund_unused_deposit(storage_deposit);
 
-        let input_request = input_request();
-        contract.register_signature_request(input_request.clone());
+        self.complete_registration(new_request, storage_deposit)
     }
 
-    #[test]
-    fn test_register_signature_request_uses_different_ids() {
-        let (mut contract, _) = setup();
+    /// Registers the request on behalf of the DAO once its proposal is confirmed to be approved
+    #[payable]
+    pub fn register_approved_signature_request(
+        &mut self,
+        request: InputRequest,
+        proposal: DaoProposal,
+    ) -> Promise {
+        ensure(
+            !self.used_proposals.contains(&proposal),
+            ContractError::ProposalAlreadyUsed,
+        );
+        // everything that may panic is checked before the deposit is carried to the callback
+        self.assert_valid_input_request(&request);
 
-        let input_request = input_request();
-        let request_1 = contract.register_signature_request(input_request.clone());
-        let request_2 = contract.register_signature_request(input_request.clone());
+        let proposal_promise = create_dao_proposal_promise(&proposal);
+        let callback_promise = create_on_dao_proposal_callback_promise(
+            request,
+            proposal,
+            env::predecessor_account_id(),
+            env::attached_deposit(),
+        );
 
-        assert_ne!(request_1.request_id, request_2.request_id);
+        proposal_promise.then(callback_promise)
     }
 
-    #[test]
-    fn test_register_signature_request_with_function_data() {
-        let (mut contract, _) = setup();
+    #[private]
+    pub fn on_dao_proposal(
+        &mut self,
+        request: InputRequest,
+        proposal: DaoProposal,
+        payer_id: AccountId,
+        deposit: NearToken,
+    ) -> PromiseOrValue<RegisterSignatureReqResponse> {
+        // https://docs.rs/near-sdk/latest/near_sdk/env/fn.promise_results_count.html
+        ensure(
+            env::promise_results_count() == 1,
+            ContractError::TooManyResults,
+        );
 
-        let mut input_request = input_request();
-        input_request.transaction_payload.function_data = Some(FunctionData {
-            function_abi: Function {
-                name: "set".to_string(),
-                inputs: vec![Param {
-                    name: "_num".to_string(),
-                    kind: ParamType::Uint(256),
-                    internal_type: Some("uint256".to_string()),
-                }],
-                outputs: vec![],
-                constant: None,
-                state_mutability: StateMutability::NonPayable,
-            },
-            arguments: vec![Token::Uint(U256([2000, 0, 0, 0]))],
+        let dao_proposal = match env::promise_result(0) {
+            PromiseResult::Successful(data) => serde_json::from_slice::<Proposal>(&data).ok(),
+            _ => None,
+        };
+
+        let is_approved = dao_proposal.map_or(false, |dao_proposal| {
+            dao_proposal.is_approved_with_commitment(&request.commitment())
         });
 
-        contract.register_signature_request(input_request.clone());
-    }
+        if !is_approved {
+            return refund_and_fail(payer_id, deposit, ContractError::ProposalNotApproved).into();
+        }
 
-    #[should_panic]
-    #[test]
-    fn test_register_signature_request_panics_on_invalid_function_arguments() {
-        let (mut contract, _) = setup();
+        if self.used_proposals.contains(&proposal) {
+            return refund_and_fail(payer_id, deposit, ContractError::ProposalAlreadyUsed).into();
+        }
 
-        let mut input_request = input_request();
-        input_request.transaction_payload.function_data = Some(FunctionData {
-            function_abi: Function {
-                name: "set".to_string(),
-                inputs: vec![Param {
-                    name: "_num".to_string(),
-                    kind: ParamType::Uint(256),
-                    internal_type: Some("uint256".to_string()),
-                }],
-                outputs: vec![],
-                constant: None,
-                state_mutability: StateMutability::NonPayable,
-            },
-            arguments: vec![],
+        let (new_request, storage_deposit) = self.with_storage_deposit(|contract| {
+            contract.used_proposals.insert(proposal.clone());
+            // the payer gets the storage deposit back once the request is removed
+            contract.add_request(request, proposal.dao_id.clone(), payer_id.clone())
         });
 
-        // must panic since no arguments are provided
-        contract.register_signature_request(input_request.clone());
-    }
+        if deposit < storage_deposit {
+            // revert changes manually, otherwise deposit would be lost
+            self.used_proposals.remove(&proposal);
+            let _ = self.remove_request(new_request.id);
 
-    #[should_panic]
-    #[test]
-    fn test_register_signature_request_panics_on_wrong_address() {
-        let (mut contract, _) = setup();
+            return refund_and_fail(payer_id, deposit, ContractError::InsufficientDeposit).into();
+        }
 
-        let mut input_request = input_request();
-        input_request.transaction_payload.to = "0xbajdo3i1o21o214".to_string();
+        let refund = deposit.saturating_sub(storage_deposit);
+        if refund > NearToken::from_yoctonear(1) {
+            Promise::new(payer_id).transfer(refund);
+        }
 
-        // must panic since address is invalid
-        contract.register_signature_request(input_request.clone());
+        PromiseOrValue::Value(self.complete_registration(new_request, storage_deposit))
     }
 
-    #[should_panic]
-    #[test]
-    fn test_register_signature_request_panics_on_small_deposit() {
-        let (mut contract, mut context) = setup();
+    pub fn get_request_commitment(&self, request: InputRequest) -> String {
+        request.commitment()
+    }
 
-        context.attached_deposit(NearToken::from_yoctonear(100));
-        testing_env!(context.build());
+    #[payable]
+    pub fn cancel_request(&mut self, request_id: RequestId) {
+        assert_deposit(NearToken::from_yoctonear(1));
 
-        let input_request = input_request();
-        contract.register_signature_request(input_request.clone());
-    }
+        let request = self.get_request_or_panic(request_id);
 
-    #[should_panic = "ERR_NOT_FOUND"]
-    #[test]
-    fn test_get_signature_panics_on_unexisted_request() {
-        let (mut contract, _) = setup();
+        ensure(
+            request.created_by == env::predecessor_account_id(),
+            ContractError::Forbidden,
+        );
 
-        let other_payload = other_payload();
-        contract.get_signature(100, other_payload);
-    }
+        let (request, history_refunds) = self.remove_request(request_id);
 
-    #[should_panic = "ERR_FORBIDDEN"]
-    #[test]
-    fn test_get_signature_panics_on_non_allowed_actor() {
-        let (mut contract, mut context) = setup();
+        // keep track of cancelled id to fail get_signature() with a distinct error
+        // storage it occupies is covered from the refund
+        let (_, tombstone_deposit) = self.with_storage_deposit(|contract| {
+            contract.cancelled_requests.insert(request_id);
+        });
 
-        let input_request = input_request();
-        let request = contract.register_signature_request(input_request.clone());
+        let refund = request.storage_deposit.saturating_sub(tombstone_deposit);
 
-        context.predecessor_account_id(user2());
-        testing_env!(context.build());
+        ContractEvent::RequestCancelled(RequestRemoved::new(&request, refund, &history_refunds))
+            .emit();
 
-        let other_payload = other_payload();
-        contract.get_signature(request.request_id, other_payload);
+        transfer_refunds(
+            [
+                (request.storage_payer_id, refund),
+                (request.created_by, request.executors_storage_deposit),
+            ]
+            .into_iter()
+            .chain(history_refunds),
+        );
     }
 
-    #[should_panic = "ERR_TIME_IS_UP"]
-    #[test]
-    fn test_get_signature_panics_on_deadline() {
-        let (mut contract, mut context) = setup();
+    #[payable]
+    pub fn add_executor(&mut self, request_id: RequestId, account_id: AccountId) {
+        let request = self.get_active_request_of_creator_or_panic(request_id);
 
-        let input_request = input_request();
-        let request = contract.register_signature_request(input_request.clone());
+        let accounts = request
+            .allowed_executors
+            .accounts_mut()
+            .unwrap_or_panic(ContractError::ExecutorsNotListed);
 
-        // one day + a second
-        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        ensure(
+            !accounts.contains(&account_id),
+            ContractError::AlreadyExecutor,
+        );
+
+        let storage_used_before = env::storage_usage();
+
+        accounts.push(account_id.clone());
+        self.requests.flush();
+        add_to_account_index(
+            &mut self.requests_by_executor,
+            account_id.clone(),
+            request_id,
+            |account_id_hash| StorageKey::RequestsByExecutorInner { account_id_hash },
+        );
+
+        let storage_used_after = env::storage_usage();
+
+        let used_storage = storage_used_after
+            .checked_sub(storage_used_before)
+            .unwrap_or_panic(ContractError::Unexpected);
+
+        let storage_deposit = calculate_deposit_for_used_storage(used_storage);
+
+        assert_deposit(storage_deposit);
+        refund_unused_deposit(storage_deposit);
+
+        let request = self.get_request_mut_or_panic(request_id);
+        request.executors_storage_deposit = request
+            .executors_storage_deposit
+            .saturating_add(storage_deposit);
+
+        ContractEvent::ExecutorAdded(ExecutorAdded {
+            request_id,
+            executor_id: account_id,
+            storage_deposit,
+        })
+        .emit();
+    }
+
+    #[payable]
+    pub fn remove_executor(&mut self, request_id: RequestId, account_id: AccountId) {
+        assert_deposit(NearToken::from_yoctonear(1));
+
+        let request = self.get_active_request_of_creator_or_panic(request_id);
+
+        let accounts = request
+            .allowed_executors
+            .accounts_mut()
+            .unwrap_or_panic(ContractError::ExecutorsNotListed);
+
+        ensure(accounts.contains(&account_id), ContractError::NotExecutor);
+        ensure(accounts.len() > 1, ContractError::NoExecutors);
+
+        let storage_used_before = env::storage_usage();
+
+        accounts.retain(|executor_id| *executor_id != account_id);
+        self.requests.flush();
+        remove_from_account_index(&mut self.requests_by_executor, &account_id, request_id);
+
+        let storage_used_after = env::storage_usage();
+
+        let freed_storage = storage_used_before
+            .checked_sub(storage_used_after)
+            .unwrap_or_panic(ContractError::Unexpected);
+
+        let request = self.get_request_mut_or_panic(request_id);
+
+        let freed_deposit = calculate_deposit_for_used_storage(freed_storage);
+
+        // the creator's deposit for added executors is returned first, the rest of the freed
+        // storage was paid with the request
+        let executors_refund = freed_deposit.min(request.executors_storage_deposit);
+        request.executors_storage_deposit = request
+            .executors_storage_deposit
+            .saturating_sub(executors_refund);
+
+        let refund = freed_deposit
+            .saturating_sub(executors_refund)
+            .min(request.storage_deposit);
+        request.storage_deposit = request.storage_deposit.saturating_sub(refund);
+
+        let (created_by, storage_payer_id) =
+            (request.created_by.clone(), request.storage_payer_id.clone());
+
+        transfer_refunds([
+            (created_by, executors_refund),
+            (storage_payer_id.clone(), refund),
+        ]);
+
+        ContractEvent::ExecutorRemoved(ExecutorRemoved {
+            request_id,
+            executor_id: account_id,
+            refunded_deposit: refund,
+            refunded_to: storage_payer_id,
+            executors_refunded_deposit: executors_refund,
+        })
+        .emit();
+    }
+
+    /// Examines up to `limit` requests starting from `from_index`, removes the expired ones
+    /// and refunds storage deposits to their creators
+    pub fn cleanup_expired(
+        &mut self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> CleanupExpiredResponse {
+        let now = env::block_timestamp();
+        let limit = limit.unwrap_or(DEFAULT_CLEANUP_LIMIT);
+
+        let mut index = from_index.unwrap_or(0);
+        let mut examined = 0;
+        let mut expired_requests = vec![];
+        let mut refunds = vec![];
+
+        while examined < limit {
+            let Some(request) = self.requests.values().nth(index as usize) else {
+                break;
+            };
+            examined += 1;
+
+            if request.is_time_exceeded(now) {
+                // removal moves the last request into the freed slot, so it's examined next
+                let request_id = request.id;
+                let (request, history_refunds) = self.remove_request(request_id);
+
+                ContractEvent::RequestExpiredCleanup(RequestRemoved::new(
+                    &request,
+                    request.storage_deposit,
+                    &history_refunds,
+                ))
+                .emit();
+
+                refunds.extend(history_refunds);
+                expired_requests.push(request);
+            } else {
+                index += 1;
+            }
+        }
+
+        let next_index = (index < self.requests.len() as u64).then_some(index);
+        let expired_request_ids = expired_requests.iter().map(|request| request.id).collect();
+
+        for request in expired_requests {
+            refunds.push((request.storage_payer_id, request.storage_deposit));
+            refunds.push((request.created_by, request.executors_storage_deposit));
+        }
+
+        transfer_refunds(refunds);
+
+        CleanupExpiredResponse {
+            removed: expired_request_ids,
+            next_index,
+        }
+    }
+
+    #[payable]
+    pub fn get_signature(
+        &mut self,
+        request_id: RequestId,
+        other_payload: OtherEip1559TransactionPayload,
+    ) -> Promise {
+        assert_deposit(
+            self.mpc_signature_deposit
+                .unwrap_or(NearToken::from_yoctonear(1)),
+        );
+        assert_gas(self.min_gas_for_signature());
+
+        let request = self.get_request_or_panic(request_id).clone();
+
+        ensure(
+            !request.is_time_exceeded(env::block_timestamp()),
+            ContractError::TimeIsUp,
+        );
+
+        ensure(
+            request.is_chain_allowed(other_payload.chain_id),
+            ContractError::ChainNotAllowed,
+        );
+
+        // executor must not be able to drain the derived account with fees
+        if let Some(gas_fee_limits) = request.gas_fee_limits_for(other_payload.chain_id) {
+            gas_fee_limits.assert_allows(&other_payload);
+        }
+
+        request.assert_access_list_allowed(&other_payload);
+        request.assert_nonce_allowed(&other_payload);
+        request.assert_usage_allowed(other_payload.chain_id);
+
+        if let Some(error) = self.nonce_error(&request, &other_payload) {
+            error.panic()
+        }
+
+        if let AllowedExecutors::DaoRole { dao_id, .. } = &request.allowed_executors {
+            // DAO authorization and the deposit query don't fit in the gas limit together
+            ensure(
+                self.mpc_signature_deposit.is_some(),
+                ContractError::MpcSignatureDepositRequired,
+            );
+            assert_gas(
+                self.min_gas_for_signature()
+                    .saturating_add(GAS_FOR_DAO_AUTHORIZATION),
+            );
+
+            let policy_promise = create_dao_policy_promise(dao_id.clone());
+            let callback_promise = create_on_dao_policy_callback_promise(
+                request_id,
+                other_payload,
+                env::predecessor_account_id(),
+                env::attached_deposit(),
+            );
+
+            return policy_promise.then(callback_promise);
+        }
+
+        ensure(
+            request.is_account_allowed(env::predecessor_account_id()),
+            ContractError::Forbidden,
+        );
+
+        self.request_signature(
+            request_id,
+            other_payload,
+            env::predecessor_account_id(),
+            env::attached_deposit(),
+        )
+    }
+
+    #[private]
+    pub fn on_dao_policy(
+        &mut self,
+        request_id: RequestId,
+        other_payload: OtherEip1559TransactionPayload,
+        executor_id: AccountId,
+        deposit: NearToken,
+    ) -> Promise {
+        // https://docs.rs/near-sdk/latest/near_sdk/env/fn.promise_results_count.html
+        ensure(
+            env::promise_results_count() == 1,
+            ContractError::TooManyResults,
+        );
+
+        let policy = match env::promise_result(0) {
+            PromiseResult::Successful(data) => serde_json::from_slice::<Policy>(&data).ok(),
+            _ => None,
+        };
+
+        let is_authorized = match (policy, self.requests.get(&request_id)) {
+            (Some(policy), Some(request)) => match &request.allowed_executors {
+                AllowedExecutors::DaoRole { role, .. } => {
+                    policy.is_member_of_role(&executor_id, role)
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+
+        if !is_authorized {
+            return refund_and_fail(executor_id, deposit, ContractError::Forbidden);
+        }
+
+        // another signature could have been requested while the policy was fetched
+        if let Some(error) = self
+            .requests
+            .get(&request_id)
+            .and_then(|request| request.usage_error(other_payload.chain_id))
+        {
+            return refund_and_fail(executor_id, deposit, error);
+        }
+
+        if let Some(error) = self
+            .requests
+            .get(&request_id)
+            .and_then(|request| self.nonce_error(request, &other_payload))
+        {
+            return refund_and_fail(executor_id, deposit, error);
+        }
+
+        self.request_signature(request_id, other_payload, executor_id, deposit)
+    }
+
+    #[private]
+    pub fn on_mpc_signature_deposit(
+        &mut self,
+        tx_hex: String,
+        request_id: RequestId,
+        derivation_path: String,
+        executor_id: AccountId,
+        deposit: NearToken,
+    ) -> Promise {
+        // https://docs.rs/near-sdk/latest/near_sdk/env/fn.promise_results_count.html
+        ensure(
+            env::promise_results_count() == 1,
+            ContractError::TooManyResults,
+        );
+
+        // MPC Contract that doesn't expose the deposit receives the whole deposit
+        let required_deposit = match env::promise_result(0) {
+            PromiseResult::Successful(data) => serde_json::from_slice::<NearToken>(&data).ok(),
+            _ => None,
+        };
+
+        let tx = vec_to_tx(
+            &tx_hex
+                .parse::<Bytes>()
+                .unwrap_or_panic(ContractError::CantDecodeTx),
+        );
+
+        // the request could have been cancelled or cleaned up in the meantime
+        let Some(request) = self.requests.get(&request_id).cloned() else {
+            return self.abort_signature(SignatureFailure {
+                request_id,
+                chain_id: tx.chain_id().unwrap_or_default().as_u64(),
+                derivation_path,
+                executor_id,
+                error: ContractError::NotFound,
+                refunded_deposit: deposit,
+            });
+        };
+
+        self.sign_with_deposit(tx, request, executor_id, deposit, required_deposit)
+    }
+
+    #[private]
+    pub fn on_refunded_failure(&self, error: ContractError) {
+        error.panic()
+    }
+
+    #[private]
+    pub fn on_get_signature(
+        &mut self,
+        tx_hex: String,
+        request_id: RequestId,
+        derivation_path: String,
+        executor_id: AccountId,
+        deposit: NearToken,
+        storage_deposit: NearToken,
+    ) -> GetSignatureResult {
+        // https://docs.rs/near-sdk/latest/near_sdk/env/fn.promise_results_count.html
+        ensure(
+            env::promise_results_count() == 1,
+            ContractError::TooManyResults,
+        );
+
+        let tx = vec_to_tx(
+            &tx_hex
+                .parse::<Bytes>()
+                .unwrap_or_panic(ContractError::CantDecodeTx),
+        );
+
+        // MPC Contract keeps the deposit once it responds, so only a failed call returns it
+        let (signed, refundable_deposit) = match env::promise_result(0) {
+            PromiseResult::Successful(data) => (
+                serde_json::from_slice::<SignatureResponse>(data.as_slice())
+                    .map_err(|_| ContractError::CantParseSignature),
+                storage_deposit,
+            ),
+            _ => (
+                Err(ContractError::SignFailed),
+                deposit.saturating_add(storage_deposit),
+            ),
+        };
+
+        let signed = signed.and_then(|signature| {
+            let mpc_signature = MpcSignature::try_from(&signature)?;
+            let signed_tx = sign_tx(&tx, &mpc_signature)?;
+
+            Ok((signature, mpc_signature, signed_tx))
+        });
+
+        // unusable signature isn't counted the same as a failed MPC call,
+        // otherwise the usage counters would stay bumped and the storage deposit kept
+        let (signature, mpc_signature, signed_tx) = match signed {
+            Ok(signed) => signed,
+            Err(error) => {
+                let failure = self.fail_signature(SignatureFailure {
+                    request_id,
+                    chain_id: tx.chain_id().unwrap_or_default().as_u64(),
+                    derivation_path,
+                    executor_id,
+                    error,
+                    refunded_deposit: refundable_deposit,
+                });
+
+                return GetSignatureResult::Failed(failure);
+            }
+        };
+
+        let tx_hash = Bytes::from(signed_tx.hash.0).to_string();
+        let evm_signature = EvmSignature {
+            r: to_bytes32(signed_tx.signature.r).to_string(),
+            s: to_bytes32(signed_tx.signature.s).to_string(),
+            v: signed_tx.signature.v,
+            y_parity: mpc_signature.recovery_id,
+        };
+
+        let storage_used_before = env::storage_usage();
+
+        self.record_signed_nonce(
+            SignerChainKey {
+                derivation_path: derivation_path.clone(),
+                chain_id: tx.chain_id().unwrap_or_default().as_u64(),
+            },
+            JsonU256(tx.nonce().copied().unwrap_or_default()),
+        );
+        self.highest_signed_nonces.flush();
+
+        let nonce_storage = env::storage_usage().saturating_sub(storage_used_before);
+        let record_storage = self.push_signature_record(
+            request_id,
+            SignatureRecord {
+                chain_id: tx.chain_id().unwrap_or_default().as_u64(),
+                tx_hash: tx_hash.clone(),
+                nonce: JsonU256(tx.nonce().copied().unwrap_or_default()),
+                gas: JsonU256(tx.gas().copied().unwrap_or_default()),
+                max_fee_per_gas: JsonU256(tx.gas_price().unwrap_or_default()),
+                max_priority_fee_per_gas: tx
+                    .as_eip1559_ref()
+                    .and_then(|tx| tx.max_priority_fee_per_gas)
+                    .map(JsonU256),
+                access_list: tx_access_list(&tx),
+                signature: evm_signature.clone(),
+                executor_id: executor_id.clone(),
+                signed_at: env::block_timestamp(),
+            },
+        );
+
+        // the deposit held for the storage could be more than needed, e.g. when the nonce entry
+        // was created by another signature in the meantime
+        let unused_storage_deposit = storage_deposit.saturating_sub(
+            calculate_deposit_for_used_storage(nonce_storage + record_storage),
+        );
+        if !unused_storage_deposit.is_zero() {
+            Promise::new(executor_id.clone()).transfer(unused_storage_deposit);
+        }
+
+        ContractEvent::SignatureProduced(SignatureProduced {
+            request_id,
+            chain_id: tx.chain_id().unwrap_or_default().as_u64(),
+            derivation_path,
+            executor_id,
+            tx_hash: tx_hash.clone(),
+        })
+        .emit();
+
+        GetSignatureResult::Signed(GetSignatureResponse {
+            tx: tx_hex,
+            signature,
+            evm_signature,
+            signed_tx: signed_tx.raw.to_string(),
+            tx_hash,
+            from: Bytes::from(signed_tx.from.0).to_string(),
+        })
+    }
+}
+
+/// Internal helpers API
+impl Contract {
+    fn add_request(
+        &mut self,
+        input_request: InputRequest,
+        creator_id: AccountId,
+        storage_payer_id: AccountId,
+    ) -> Request {
+        self.assert_valid_input_request(&input_request);
+
+        let current_request_id = self.next_request_id;
+        self.next_request_id += 1;
+
+        let internal_request = Request {
+            id: current_request_id,
+            derivation_path: create_derivation_path(
+                &creator_id,
+                input_request.derivation_seed_number,
+            ),
+            created_by: creator_id,
+            created_at: block_timestamp(),
+            created_at_block: env::block_height(),
+            allowed_executors: input_request
+                .executors()
+                .unwrap_or_panic(ContractError::NoExecutors)
+                .deduplicated(),
+            payload: input_request.transaction_payload.into(),
+            key_version: input_request.key_version.unwrap_or(0),
+            deadline: block_timestamp()
+                .checked_add(self.request_ttl(input_request.ttl))
+                .unwrap_or_panic(ContractError::InvalidTtl),
+            signature_count: 0,
+            recorded_signature_count: 0,
+            storage_deposit: NearToken::from_yoctonear(0),
+            storage_payer_id,
+            executors_storage_deposit: NearToken::from_yoctonear(0),
+            allowed_chain_ids: input_request.allowed_chain_ids.map(|mut chain_ids| {
+                chain_ids.sort_unstable();
+                chain_ids.dedup();
+                chain_ids
+            }),
+            gas_fee_limits: input_request.gas_fee_limits,
+            chain_gas_fee_limits: input_request.chain_gas_fee_limits,
+            usage_policy: input_request.usage_policy.unwrap_or_default(),
+            chain_signature_counts: BTreeMap::new(),
+            transaction_type: input_request.transaction_type.unwrap_or_default(),
+            allow_executor_access_list: input_request.allow_executor_access_list.unwrap_or(true),
+            nonce_override_policy: input_request.nonce_override_policy.unwrap_or_default(),
+        };
+        self.insert_request(internal_request.clone());
+
+        internal_request
+    }
+
+    fn assert_valid_input_request(&self, input_request: &InputRequest) {
+        ensure(
+            input_request.allowed_executors.is_none() || input_request.allowed_account_id.is_none(),
+            ContractError::ConflictingExecutors,
+        );
+
+        ensure(
+            input_request
+                .executors()
+                .map_or(false, |allowed_executors| allowed_executors.is_valid()),
+            ContractError::NoExecutors,
+        );
+
+        ensure(
+            input_request
+                .allowed_chain_ids
+                .as_ref()
+                .map_or(true, |chain_ids| !chain_ids.is_empty()),
+            ContractError::NoChainIds,
+        );
+
+        ensure(
+            input_request
+                .usage_policy
+                .map_or(true, |usage_policy| usage_policy.is_valid()),
+            ContractError::InvalidUsagePolicy,
+        );
+
+        ensure(
+            input_request.transaction_type != Some(TransactionType::Legacy)
+                || input_request.transaction_payload.access_list.is_none(),
+            ContractError::AccessListNotSupported,
+        );
+
+        self.request_ttl(input_request.ttl);
+
+        let _: BaseEip1559TransactionPayload = input_request.transaction_payload.clone().into();
+    }
+
+    // Returns the deposit for the storage occupied by the changes made in `write`
+    fn with_storage_deposit<T>(&mut self, write: impl FnOnce(&mut Self) -> T) -> (T, NearToken) {
+        let storage_used_before = env::storage_usage();
+        let result = write(self);
+        let storage_used_after = env::storage_usage();
+
+        let used_storage = storage_used_after
+            .checked_sub(storage_used_before)
+            .unwrap_or_panic(ContractError::Unexpected);
+
+        (result, calculate_deposit_for_used_storage(used_storage))
+    }
+
+    fn complete_registration(
+        &mut self,
+        request: Request,
+        storage_deposit: NearToken,
+    ) -> RegisterSignatureReqResponse {
+        // recorded to be refunded once the request is removed
+        self.get_request_mut_or_panic(request.id).storage_deposit = storage_deposit;
+        ContractEvent::RequestRegistered(RequestRegistered::new(&request, storage_deposit)).emit();
+
+        self.create_register_response(request)
+    }
+
+    fn create_register_response(&self, request: Request) -> RegisterSignatureReqResponse {
+        let derived_address = self
+            .derive_account(request.derivation_path.clone(), request.key_version)
+            .map(|derived_account| derived_account.address);
+
+        RegisterSignatureReqResponse {
+            request_id: request.id,
+            deadline: request.deadline,
+            created_by: request.created_by,
+            created_at: request.created_at,
+            created_at_block: request.created_at_block,
+            derivation_path: request.derivation_path,
+            mpc_account_id: self.mpc_contract_id.clone(),
+            allowed_account_id: match request.allowed_executors.accounts() {
+                [account_id] => Some(account_id.clone()),
+                _ => None,
+            },
+            allowed_executors: request.allowed_executors,
+            derived_address,
+        }
+    }
+
+    fn derive_account(&self, derivation_path: String, key_version: u32) -> Option<DerivedAccount> {
+        // only the cached key version can be derived, other versions use different root keys
+        if key_version != MPC_PUBLIC_KEY_VERSION {
+            return None;
+        }
+
+        let mpc_public_key = parse_mpc_public_key(self.mpc_public_key.as_ref()?)?;
+
+        // MPC Contract derives keys for the predecessor, which is this contract
+        let epsilon = derive_epsilon(&env::current_account_id(), &derivation_path);
+        let public_key = derive_public_key(&mpc_public_key, epsilon);
+
+        Some(DerivedAccount {
+            derivation_path,
+            public_key: to_near_public_key(&public_key),
+            address: Bytes::from(to_evm_address(&public_key).0).to_string(),
+        })
+    }
+
+    pub(crate) fn insert_request(&mut self, request: Request) {
+        add_to_account_index(
+            &mut self.requests_by_creator,
+            request.created_by.clone(),
+            request.id,
+            |account_id_hash| StorageKey::RequestsByCreatorInner { account_id_hash },
+        );
+        for executor_id in request.allowed_executors.accounts() {
+            add_to_account_index(
+                &mut self.requests_by_executor,
+                executor_id.clone(),
+                request.id,
+                |account_id_hash| StorageKey::RequestsByExecutorInner { account_id_hash },
+            );
+        }
+
+        self.requests.insert(request.id, request);
+        // this is required as collections don't write state immediately
+        // Bug4 -> https://docs.near.org/build/smart-contracts/anatomy/collections#error-prone-patterns
+        self.requests.flush();
+    }
+
+    // Returns the removed request and the storage deposits of its history records,
+    // which belong to the executors that paid for them
+    pub(crate) fn remove_request(
+        &mut self,
+        request_id: RequestId,
+    ) -> (Request, Vec<(AccountId, NearToken)>) {
+        let request = self
+            .requests
+            .remove(&request_id)
+            .unwrap_or_panic(ContractError::NotFound);
+
+        remove_from_account_index(
+            &mut self.requests_by_creator,
+            &request.created_by,
+            request.id,
+        );
+        for executor_id in request.allowed_executors.accounts() {
+            remove_from_account_index(&mut self.requests_by_executor, executor_id, request.id);
+        }
+        let history_refunds = (0..request.recorded_signature_count.min(MAX_SIGNATURE_HISTORY))
+            .filter_map(|slot| {
+                self.signature_history
+                    .remove(&SignatureRecordKey { request_id, slot })
+            })
+            .map(|record| {
+                let refund = calculate_deposit_for_used_storage(record.storage_usage());
+                (record.executor_id, refund)
+            })
+            .collect();
+
+        (request, history_refunds)
+    }
+
+    // History is bounded, the oldest record is overwritten once it's full
+    // Returns the storage charged for the record, the overwritten one is refunded to its executor
+    fn push_signature_record(
+        &mut self,
+        request_id: RequestId,
+        record: SignatureRecord,
+    ) -> StorageUsage {
+        // the request could have been cancelled or cleaned up in the meantime
+        let Some(request) = self.requests.get_mut(&request_id) else {
+            return 0;
+        };
+
+        let slot = request.recorded_signature_count % MAX_SIGNATURE_HISTORY;
+        request.recorded_signature_count += 1;
+
+        let storage_usage = record.storage_usage();
+
+        if let Some(overwritten) = self
+            .signature_history
+            .insert(SignatureRecordKey { request_id, slot }, record)
+        {
+            Promise::new(overwritten.executor_id.clone()).transfer(
+                calculate_deposit_for_used_storage(overwritten.storage_usage()),
+            );
+        }
+
+        storage_usage
+    }
+
+    fn request_ttl(&self, ttl: Option<Duration>) -> Duration {
+        match ttl {
+            Some(ttl) => {
+                ensure(
+                    self.request_ttl_limits.contains(ttl),
+                    ContractError::InvalidTtl,
+                );
+
+                ttl
+            }
+            None => self.request_ttl_limits.clamp(DEFAULT_REQUEST_TTL),
+        }
+    }
+
+    fn request_signature(
+        &mut self,
+        request_id: RequestId,
+        other_payload: OtherEip1559TransactionPayload,
+        executor_id: AccountId,
+        deposit: NearToken,
+    ) -> Promise {
+        let request = self.get_request_mut_or_panic(request_id);
+
+        request.record_signature(other_payload.chain_id);
+        let request = request.clone();
+
+        ContractEvent::SignatureRequested(SignatureRequested {
+            request_id,
+            chain_id: other_payload.chain_id,
+            derivation_path: request.derivation_path.clone(),
+            executor_id: executor_id.clone(),
+            nonce: request.nonce_for(&other_payload),
+        })
+        .emit();
+
+        let tx = create_tx(
+            request.transaction_type,
+            request.payload.clone(),
+            other_payload,
+        );
+
+        match self.mpc_signature_deposit {
+            Some(required_deposit) => {
+                self.sign_with_deposit(tx, request, executor_id, deposit, Some(required_deposit))
+            }
+            None => create_mpc_signature_deposit_promise(self.mpc_contract_id.clone()).then(
+                create_on_mpc_signature_deposit_callback_promise(
+                    tx,
+                    request_id,
+                    request.derivation_path,
+                    executor_id,
+                    deposit,
+                ),
+            ),
+        }
+    }
+
+    // Forwards only the deposit required by MPC Contract and refunds the rest to the executor
+    // The whole deposit is forwarded if MPC Contract doesn't expose the required one
+    fn sign_with_deposit(
+        &mut self,
+        tx: TypedTransaction,
+        request: Request,
+        executor_id: AccountId,
+        deposit: NearToken,
+        required_deposit: Option<NearToken>,
+    ) -> Promise {
+        let chain_id = tx.chain_id().unwrap_or_default().as_u64();
+        // held until the signature is produced, as only then the nonce and the history are recorded
+        let storage_deposit = self.signature_storage_deposit(&request, &tx, &executor_id);
+        let sign_deposit = required_deposit.unwrap_or(deposit.saturating_sub(storage_deposit));
+
+        if sign_deposit.saturating_add(storage_deposit) > deposit {
+            return self.abort_signature(SignatureFailure {
+                request_id: request.id,
+                chain_id,
+                derivation_path: request.derivation_path,
+                executor_id,
+                error: ContractError::InsufficientDeposit,
+                refunded_deposit: deposit,
+            });
+        }
+
+        let excess_deposit = deposit
+            .saturating_sub(sign_deposit)
+            .saturating_sub(storage_deposit);
+        if !excess_deposit.is_zero() {
+            Promise::new(executor_id.clone()).transfer(excess_deposit);
+        }
+
+        let request_id = request.id;
+        let derivation_path = request.derivation_path.clone();
+        let sign_promise = create_sign_promise(
+            self.mpc_contract_id.clone(),
+            tx.clone(),
+            request,
+            sign_deposit,
+        );
+        let callback_promise = create_on_sign_callback_promise(
+            tx,
+            request_id,
+            derivation_path,
+            executor_id,
+            sign_deposit,
+            storage_deposit,
+        );
+
+        sign_promise.then(callback_promise)
+    }
+
+    // Querying the deposit from MPC Contract takes an extra cross-contract call
+    fn min_gas_for_signature(&self) -> Gas {
+        match self.mpc_signature_deposit {
+            Some(_) => MIN_GAS_FOR_GET_SIGNATURE,
+            None => MIN_GAS_FOR_GET_SIGNATURE.saturating_add(GAS_FOR_SIGNATURE_DEPOSIT_QUERY),
+        }
+    }
+
+    // Failed MPC call doesn't produce a signature, so it isn't counted towards the usage policy
+    // and the deposit is returned to the executor instead of being kept by the contract
+    fn fail_signature(&mut self, failure: SignatureFailure) -> SignatureFailure {
+        self.revert_signature(&failure);
+
+        if !failure.refunded_deposit.is_zero() {
+            Promise::new(failure.executor_id.clone()).transfer(failure.refunded_deposit);
+        }
+
+        failure
+    }
+
+    // Same as fail_signature(), but the call fails once the deposit is refunded
+    fn abort_signature(&mut self, failure: SignatureFailure) -> Promise {
+        self.revert_signature(&failure);
+
+        refund_and_fail(failure.executor_id, failure.refunded_deposit, failure.error)
+    }
+
+    fn revert_signature(&mut self, failure: &SignatureFailure) {
+        // the request could have been cancelled or cleaned up in the meantime
+        if let Some(request) = self.requests.get_mut(&failure.request_id) {
+            request.revert_signature(failure.chain_id);
+        }
+
+        ContractEvent::SignatureFailed(failure.clone()).emit();
+    }
+
+    // Nonce lower than the highest signed one can't be included in a block anymore,
+    // the same nonce is allowed to replace a pending transaction
+    // Nonce provided by the executor can't run far ahead, otherwise it would block the derived
+    // account for every request that shares it
+    fn nonce_error(
+        &self,
+        request: &Request,
+        other_payload: &OtherEip1559TransactionPayload,
+    ) -> Option<ContractError> {
+        let nonce = request.nonce_for(other_payload);
+        let highest_nonce = self
+            .highest_signed_nonces
+            .get(&SignerChainKey {
+                derivation_path: request.derivation_path.clone(),
+                chain_id: other_payload.chain_id,
+            })
+            .copied();
+
+        if highest_nonce.map_or(false, |highest_nonce| nonce < highest_nonce) {
+            return Some(ContractError::StaleNonce);
+        }
+
+        let max_nonce = highest_nonce
+            .map_or(request.payload.nonce, |highest_nonce| {
+                highest_nonce.max(request.payload.nonce)
+            })
+            .0
+            .saturating_add(MAX_NONCE_GAP.into());
+
+        (other_payload.nonce.is_some() && nonce.0 > max_nonce)
+            .then_some(ContractError::NonceTooHigh)
+    }
+
+    // Executor pays for the storage of the highest nonce once the derived account is signed for
+    // on a new chain, and for every history record, the overwritten record is refunded to
+    // the executor that paid for it
+    fn signature_storage_deposit(
+        &self,
+        request: &Request,
+        tx: &TypedTransaction,
+        executor_id: &AccountId,
+    ) -> NearToken {
+        let signer_chain_key = SignerChainKey {
+            derivation_path: request.derivation_path.clone(),
+            chain_id: tx.chain_id().unwrap_or_default().as_u64(),
+        };
+        let nonce_storage = if self.highest_signed_nonces.contains_key(&signer_chain_key) {
+            0
+        } else {
+            signer_chain_key.entry_storage_usage()
+        };
+        let history_storage =
+            SignatureRecord::entry_storage_usage(executor_id, tx_access_list(tx).as_deref());
+
+        calculate_deposit_for_used_storage(nonce_storage + history_storage)
+    }
+
+    fn record_signed_nonce(&mut self, signer_chain_key: SignerChainKey, nonce: JsonU256) {
+        let highest_nonce = self
+            .highest_signed_nonces
+            .entry(signer_chain_key)
+            .or_insert(nonce);
+
+        if nonce > *highest_nonce {
+            *highest_nonce = nonce;
+        }
+    }
+
+    fn get_active_request_of_creator_or_panic(&mut self, request_id: RequestId) -> &mut Request {
+        let request = self.get_request_mut_or_panic(request_id);
+
+        ensure(
+            request.created_by == env::predecessor_account_id(),
+            ContractError::Forbidden,
+        );
+        ensure(
+            !request.is_time_exceeded(env::block_timestamp()),
+            ContractError::TimeIsUp,
+        );
+
+        request
+    }
+
+    fn get_request_or_panic(&self, request_id: RequestId) -> &Request {
+        ensure(
+            !self.cancelled_requests.contains(&request_id),
+            ContractError::RequestCancelled,
+        );
+
+        self.requests
+            .get(&request_id)
+            .unwrap_or_panic(ContractError::NotFound)
+    }
+
+    // get_mut marks the entry as changed, so it's only used when the request is updated
+    fn get_request_mut_or_panic(&mut self, request_id: RequestId) -> &mut Request {
+        ensure(
+            !self.cancelled_requests.contains(&request_id),
+            ContractError::RequestCancelled,
+        );
+
+        self.requests
+            .get_mut(&request_id)
+            .unwrap_or_panic(ContractError::NotFound)
+    }
+
+    fn paginate_requests<'a>(
+        &self,
+        request_ids: impl Iterator<Item = &'a RequestId>,
+        status: Option<RequestStatus>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Request> {
+        let now = env::block_timestamp();
+
+        request_ids
+            .filter_map(|request_id| self.requests.get(request_id))
+            .filter(|request| status.map_or(true, |status| request.status(now) == status))
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use constants::{MAX_SIGNED_CHAINS, ONE_MINUTE_NANOS};
+    use ethers_core::{
+        abi::{Function, Param, ParamType, StateMutability, Token},
+        k256::ProjectivePoint,
+        types::U256,
+    };
+    use near_sdk::{
+        mock::{MockAction, Receipt},
+        test_utils::{get_created_receipts, get_logs, VMContextBuilder},
+        testing_env, Gas, NearToken,
+    };
+    use primitives::{
+        AccessListEntry, FunctionData, GasFeeLimits, InputTransactionPayload, NonceOverridePolicy,
+        OtherEip1559TransactionPayload, UsagePolicy,
+    };
+
+    fn current() -> AccountId {
+        AccountId::from_str("current").unwrap()
+    }
+
+    fn user1() -> AccountId {
+        AccountId::from_str("user1").unwrap()
+    }
+
+    fn user2() -> AccountId {
+        AccountId::from_str("user2").unwrap()
+    }
+
+    fn owner() -> AccountId {
+        AccountId::from_str("owner").unwrap()
+    }
+
+    fn signer() -> AccountId {
+        AccountId::from_str("signer").unwrap()
+    }
+
+    fn setup() -> (Contract, VMContextBuilder) {
+        let mut context = VMContextBuilder::new();
+        let contract = Contract::new(signer(), Some(owner()));
+
+        context.current_account_id(current());
+        context.account_balance(NearToken::from_near(1));
+        context.attached_deposit(NearToken::from_millinear(20));
+        context.predecessor_account_id(user1());
+        context.block_timestamp(0);
+        context.prepaid_gas(Gas::from_tgas(300));
+
+        testing_env!(context.build());
+
+        (contract, context)
+    }
+
+    fn input_request() -> InputRequest {
+        InputRequest {
+            allowed_executors: Some(AllowedExecutors::Accounts(vec![user1()])),
+            allowed_account_id: None,
+            derivation_seed_number: 0,
+            transaction_payload: InputTransactionPayload {
+                to: Some("0x0000000000000000000000000000000000000000".to_string()),
+                function_data: None,
+                deployment_data: None,
+                value: None,
+                nonce: JsonU256::from(0),
+                access_list: None,
+            },
+            key_version: None,
+            ttl: None,
+            allowed_chain_ids: None,
+            gas_fee_limits: None,
+            chain_gas_fee_limits: None,
+            usage_policy: None,
+            transaction_type: None,
+            allow_executor_access_list: None,
+            nonce_override_policy: None,
+        }
+    }
+
+    fn other_payload() -> OtherEip1559TransactionPayload {
+        OtherEip1559TransactionPayload {
+            chain_id: 1,
+            gas: Some(JsonU256::from(42_000)),
+            max_fee_per_gas: JsonU256::from(120_000),
+            max_priority_fee_per_gas: JsonU256::from(120_000),
+            nonce: None,
+            access_list: None,
+        }
+    }
+
+    #[test]
+    fn test_setup_succeeds() {
+        setup();
+    }
+
+    #[test]
+    fn test_register_signature_request() {
+        let (mut contract, _) = setup();
+
+        let input_request = input_request();
+        contract.register_signature_request(input_request.clone());
+    }
+
+    #[test]
+    fn test_register_signature_request_uses_different_ids() {
+        let (mut contract, _) = setup();
+
+        let input_request = input_request();
+        let request_1 = contract.register_signature_request(input_request.clone());
+        let request_2 = contract.register_signature_request(input_request.clone());
+
+        assert_ne!(request_1.request_id, request_2.request_id);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_register_signature_request_with_function_data() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.transaction_payload.function_data = Some(FunctionData {
+            function_abi: Function {
+                name: "set".to_string(),
+                inputs: vec![Param {
+                    name: "_num".to_string(),
+                    kind: ParamType::Uint(256),
+                    internal_type: Some("uint256".to_string()),
+                }],
+                outputs: vec![],
+                constant: None,
+                state_mutability: StateMutability::NonPayable,
+            },
+            arguments: vec![Token::Uint(U256([2000, 0, 0, 0]))],
+        });
+
+        contract.register_signature_request(input_request.clone());
+    }
+
+    #[should_panic]
+    #[test]
+    #[allow(deprecated)]
+    fn test_register_signature_request_panics_on_invalid_function_arguments() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.transaction_payload.function_data = Some(FunctionData {
+            function_abi: Function {
+                name: "set".to_string(),
+                inputs: vec![Param {
+                    name: "_num".to_string(),
+                    kind: ParamType::Uint(256),
+                    internal_type: Some("uint256".to_string()),
+                }],
+                outputs: vec![],
+                constant: None,
+                state_mutability: StateMutability::NonPayable,
+            },
+            arguments: vec![],
+        });
+
+        // must panic since no arguments are provided
+        contract.register_signature_request(input_request.clone());
+    }
+
+    #[should_panic]
+    #[test]
+    fn test_register_signature_request_panics_on_wrong_address() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.transaction_payload.to = Some("0xbajdo3i1o21o214".to_string());
+
+        // must panic since address is invalid
+        contract.register_signature_request(input_request.clone());
+    }
+
+    #[should_panic]
+    #[test]
+    fn test_register_signature_request_panics_on_small_deposit() {
+        let (mut contract, mut context) = setup();
+
+        context.attached_deposit(NearToken::from_yoctonear(100));
+        testing_env!(context.build());
+
+        let input_request = input_request();
+        contract.register_signature_request(input_request.clone());
+    }
+
+    #[test]
+    fn test_get_request() {
+        let (mut contract, mut context) = setup();
+
+        context.block_timestamp(1_000);
+        context.block_height(10);
+        testing_env!(context.build());
+
+        let input_request = input_request();
+        let response = contract.register_signature_request(input_request.clone());
+
+        let request = contract.get_request(response.request_id).unwrap();
+
+        assert_eq!(request.id, response.request_id);
+        assert_eq!(request.created_by, user1());
+        assert_eq!(request.created_at, 1_000);
+        assert_eq!(request.created_at_block, 10);
+        assert_eq!(response.created_by, user1());
+        assert_eq!(response.created_at, 1_000);
+        assert_eq!(response.created_at_block, 10);
+        assert_eq!(
+            request.allowed_executors,
+            AllowedExecutors::Accounts(vec![user1()])
+        );
+        assert_eq!(request.derivation_path, response.derivation_path);
+        assert_eq!(request.deadline, response.deadline);
+        assert_eq!(request.key_version, 0);
+        assert_eq!(
+            request.payload.to,
+            Some("0x0000000000000000000000000000000000000000".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_request_returns_none_on_unexisted_request() {
+        let (contract, _) = setup();
+
+        assert!(contract.get_request(100).is_none());
+    }
+
+    #[test]
+    fn test_list_requests_paginates() {
+        let (mut contract, _) = setup();
+
+        for _ in 0..5 {
+            contract.register_signature_request(input_request());
+        }
+
+        let requests = contract.list_requests(None, Some(1), Some(3));
+
+        assert_eq!(
+            requests
+                .iter()
+                .map(|request| request.id)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_list_requests_filters_by_status() {
+        let (mut contract, mut context) = setup();
+
+        let request_1 = contract.register_signature_request(input_request());
+
+        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        testing_env!(context.build());
+
+        let request_2 = contract.register_signature_request(input_request());
+        let request_3 = contract.register_signature_request(input_request());
+        contract
+            .requests
+            .get_mut(&request_3.request_id)
+            .unwrap()
+            .signature_count = 1;
+
+        let expired = contract.list_requests(Some(RequestStatus::Expired), None, None);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, request_1.request_id);
+
+        let active = contract.list_requests(Some(RequestStatus::Active), None, None);
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, request_2.request_id);
+
+        let used = contract.list_requests(Some(RequestStatus::Used), None, None);
+        assert_eq!(used.len(), 1);
+        assert_eq!(used[0].id, request_3.request_id);
+    }
+
+    #[test]
+    fn test_list_requests_by_creator_and_executor() {
+        let (mut contract, mut context) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_executors = Some(AllowedExecutors::Accounts(vec![user2()]));
+        let request_1 = contract.register_signature_request(input_request.clone());
+
+        context.predecessor_account_id(user2());
+        testing_env!(context.build());
+
+        let request_2 = contract.register_signature_request(input_request.clone());
+
+        let created_by_user1 = contract.list_requests_by_creator(user1(), None, None, None);
+        assert_eq!(created_by_user1.len(), 1);
+        assert_eq!(created_by_user1[0].id, request_1.request_id);
+
+        let created_by_user2 = contract.list_requests_by_creator(user2(), None, None, None);
+        assert_eq!(created_by_user2.len(), 1);
+        assert_eq!(created_by_user2[0].id, request_2.request_id);
+
+        let executed_by_user2 = contract.list_requests_for_executor(user2(), None, None, None);
+        assert_eq!(executed_by_user2.len(), 2);
+
+        assert!(contract
+            .list_requests_for_executor(user1(), None, None, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_register_signature_request_with_ttl() {
+        let (mut contract, mut context) = setup();
+
+        context.block_timestamp(1_000);
+        testing_env!(context.build());
+
+        let mut input_request = input_request();
+        input_request.ttl = Some(5 * ONE_MINUTE_NANOS);
+
+        let response = contract.register_signature_request(input_request);
+
+        assert_eq!(response.deadline, 1_000 + 5 * ONE_MINUTE_NANOS);
+    }
+
+    #[should_panic = "ERR_INVALID_TTL"]
+    #[test]
+    fn test_register_signature_request_panics_on_ttl_out_of_limits() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.ttl = Some(DEFAULT_MAX_REQUEST_TTL + 1);
+
+        contract.register_signature_request(input_request);
+    }
+
+    #[test]
+    fn test_set_request_ttl_limits() {
+        let (mut contract, mut context) = setup();
+
+        context.predecessor_account_id(owner());
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.set_request_ttl_limits(RequestTtlLimits {
+            min: ONE_MINUTE_NANOS,
+            max: 10 * ONE_MINUTE_NANOS,
+        });
+
+        assert_eq!(contract.get_request_ttl_limits().max, 10 * ONE_MINUTE_NANOS);
+
+        context.attached_deposit(NearToken::from_millinear(10));
+        testing_env!(context.build());
+
+        // default ttl of one day is clamped into the new limits
+        let response = contract.register_signature_request(input_request());
+        assert_eq!(response.deadline, 10 * ONE_MINUTE_NANOS);
+    }
+
+    #[should_panic = "ERR_NOT_OWNER"]
+    #[test]
+    fn test_set_request_ttl_limits_panics_on_non_owner() {
+        let (mut contract, mut context) = setup();
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.set_request_ttl_limits(RequestTtlLimits {
+            min: ONE_MINUTE_NANOS,
+            max: 10 * ONE_MINUTE_NANOS,
+        });
+    }
+
+    #[should_panic = "ERR_INVALID_TTL_LIMITS"]
+    #[test]
+    fn test_set_request_ttl_limits_panics_on_invalid_limits() {
+        let (mut contract, mut context) = setup();
+
+        context.predecessor_account_id(owner());
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.set_request_ttl_limits(RequestTtlLimits {
+            min: 10 * ONE_MINUTE_NANOS,
+            max: ONE_MINUTE_NANOS,
+        });
+    }
+
+    #[should_panic = "ERR_INVALID_TTL_LIMITS"]
+    #[test]
+    fn test_set_request_ttl_limits_panics_on_unbounded_max() {
+        let (mut contract, mut context) = setup();
+
+        context.predecessor_account_id(owner());
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.set_request_ttl_limits(RequestTtlLimits {
+            min: ONE_MINUTE_NANOS,
+            max: u64::MAX,
+        });
+    }
+
+    #[test]
+    fn test_cancel_request() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+        let storage_deposit = contract
+            .get_request(request.request_id)
+            .unwrap()
+            .storage_deposit;
+
+        assert!(!storage_deposit.is_zero());
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.cancel_request(request.request_id);
+
+        assert!(contract.get_request(request.request_id).is_none());
+        assert!(contract
+            .list_requests_by_creator(user1(), None, None, None)
+            .is_empty());
+        assert!(contract
+            .list_requests_for_executor(user1(), None, None, None)
+            .is_empty());
+
+        let receipts = get_created_receipts();
+        let refund = receipts.last().unwrap();
+
+        assert_eq!(refund.receiver_id, user1());
+        match &refund.actions[..] {
+            [MockAction::Transfer { deposit, .. }] => {
+                assert!(*deposit > NearToken::from_yoctonear(0));
+                assert!(*deposit <= storage_deposit);
+            }
+            _ => panic!("Refund must be a single transfer"),
+        }
+    }
+
+    #[should_panic = "ERR_FORBIDDEN"]
+    #[test]
+    fn test_cancel_request_panics_on_non_creator() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        context.predecessor_account_id(user2());
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.cancel_request(request.request_id);
+    }
+
+    #[should_panic = "ERR_REQUEST_CANCELLED"]
+    #[test]
+    fn test_get_signature_panics_on_cancelled_request() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.cancel_request(request.request_id);
+        contract.get_signature(request.request_id, other_payload());
+    }
+
+    #[should_panic = "ERR_NO_EXECUTORS"]
+    #[test]
+    fn test_register_signature_request_panics_on_empty_executors() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_executors = Some(AllowedExecutors::Accounts(vec![]));
+
+        contract.register_signature_request(input_request);
+    }
+
+    #[test]
+    fn test_register_signature_request_with_allowed_account_id() {
+        let (mut contract, _) = setup();
+
+        let request: InputRequest = serde_json::from_value(serde_json::json!({
+            "allowed_account_id": user2(),
+            "derivation_seed_number": 0,
+            "transaction_payload": {
+                "to": "0x0000000000000000000000000000000000000000",
+                "nonce": "0"
+            }
+        }))
+        .unwrap();
+
+        let response = contract.register_signature_request(request);
+
+        assert_eq!(response.allowed_account_id, Some(user2()));
+        assert_eq!(
+            response.allowed_executors,
+            AllowedExecutors::Accounts(vec![user2()])
+        );
+    }
+
+    #[test]
+    fn test_register_signature_request_returns_allowed_account_id_for_single_executor() {
+        let (mut contract, _) = setup();
+
+        let response = contract.register_signature_request(input_request());
+        assert_eq!(response.allowed_account_id, Some(user1()));
+
+        let mut input_request = input_request();
+        input_request.allowed_executors = Some(AllowedExecutors::Anyone);
+
+        let response = contract.register_signature_request(input_request);
+        assert_eq!(response.allowed_account_id, None);
+    }
+
+    #[should_panic = "ERR_CONFLICTING_EXECUTORS"]
+    #[test]
+    fn test_register_signature_request_panics_on_conflicting_executors() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_account_id = Some(user2());
+
+        contract.register_signature_request(input_request);
+    }
+
+    #[should_panic = "ERR_NO_EXECUTORS"]
+    #[test]
+    fn test_register_signature_request_panics_without_executors() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_executors = None;
+
+        contract.register_signature_request(input_request);
+    }
+
+    #[test]
+    fn test_register_signature_request_with_many_executors() {
+        let (mut contract, mut context) = setup();
+
+        context.attached_deposit(NearToken::from_millinear(20));
+        testing_env!(context.build());
+
+        let mut input_request = input_request();
+        input_request.allowed_executors =
+            Some(AllowedExecutors::Accounts(vec![user1(), user2(), user1()]));
+
+        let response = contract.register_signature_request(input_request);
+        let request = contract.get_request(response.request_id).unwrap();
+
+        // duplicates are dropped
+        assert_eq!(
+            request.allowed_executors,
+            AllowedExecutors::Accounts(vec![user1(), user2()])
+        );
+        assert!(request.is_account_allowed(user1()));
+        assert!(request.is_account_allowed(user2()));
+        assert!(!request.is_account_allowed(current()));
+
+        assert_eq!(
+            contract
+                .list_requests_for_executor(user2(), None, None, None)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_register_signature_request_allowing_anyone() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_executors = Some(AllowedExecutors::Anyone);
+
+        let response = contract.register_signature_request(input_request);
+        let request = contract.get_request(response.request_id).unwrap();
+
+        assert!(request.is_account_allowed(user2()));
+        assert!(request.is_account_allowed(current()));
+    }
+
+    #[test]
+    fn test_add_and_remove_executor() {
+        let (mut contract, mut context) = setup();
+
+        let response = contract.register_signature_request(input_request());
+        let initial_storage_deposit = contract
+            .get_request(response.request_id)
+            .unwrap()
+            .storage_deposit;
+
+        contract.add_executor(response.request_id, user2());
+
+        let request = contract.get_request(response.request_id).unwrap();
+        assert!(request.is_account_allowed(user2()));
+        assert_eq!(request.storage_deposit, initial_storage_deposit);
+        assert!(!request.executors_storage_deposit.is_zero());
+        assert_eq!(
+            contract
+                .list_requests_for_executor(user2(), None, None, None)
+                .len(),
+            1
+        );
+
+        let events = event_data("executor_added");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["request_id"], response.request_id);
+        assert_eq!(events[0]["executor_id"], user2().to_string());
+        assert_eq!(
+            events[0]["storage_deposit"],
+            request.executors_storage_deposit.as_yoctonear().to_string()
+        );
+        let executors_storage_deposit = request.executors_storage_deposit;
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.remove_executor(response.request_id, user2());
+
+        let request = contract.get_request(response.request_id).unwrap();
+        assert!(!request.is_account_allowed(user2()));
+        assert_eq!(request.storage_deposit, initial_storage_deposit);
+        assert!(request.executors_storage_deposit.is_zero());
+        assert!(contract
+            .list_requests_for_executor(user2(), None, None, None)
+            .is_empty());
+
+        let events = event_data("executor_removed");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["request_id"], response.request_id);
+        assert_eq!(events[0]["executor_id"], user2().to_string());
+        assert_eq!(events[0]["refunded_deposit"], "0");
+        assert_eq!(events[0]["refunded_to"], user1().to_string());
+        assert_eq!(
+            events[0]["executors_refunded_deposit"],
+            executors_storage_deposit.as_yoctonear().to_string()
+        );
+    }
+
+    #[should_panic = "ERR_FORBIDDEN"]
+    #[test]
+    fn test_add_executor_panics_on_non_creator() {
+        let (mut contract, mut context) = setup();
+
+        let response = contract.register_signature_request(input_request());
+
+        context.predecessor_account_id(user2());
+        testing_env!(context.build());
+
+        contract.add_executor(response.request_id, user2());
+    }
+
+    #[should_panic = "ERR_TIME_IS_UP"]
+    #[test]
+    fn test_add_executor_panics_on_expired_request() {
+        let (mut contract, mut context) = setup();
+
+        let response = contract.register_signature_request(input_request());
+
+        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        testing_env!(context.build());
+
+        contract.add_executor(response.request_id, user2());
+    }
+
+    #[should_panic = "ERR_EXECUTORS_NOT_LISTED"]
+    #[test]
+    fn test_add_executor_panics_when_anyone_is_allowed() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_executors = Some(AllowedExecutors::Anyone);
+
+        let response = contract.register_signature_request(input_request);
+
+        contract.add_executor(response.request_id, user2());
+    }
+
+    #[should_panic = "ERR_NO_EXECUTORS"]
+    #[test]
+    fn test_remove_executor_panics_on_last_executor() {
+        let (mut contract, mut context) = setup();
+
+        let response = contract.register_signature_request(input_request());
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.remove_executor(response.request_id, user1());
+    }
+
+    fn dao() -> AccountId {
+        AccountId::from_str("dao").unwrap()
+    }
+
+    fn dao_role_request() -> InputRequest {
+        let mut input_request = input_request();
+        input_request.allowed_executors = Some(AllowedExecutors::DaoRole {
+            dao_id: dao(),
+            role: "council".to_string(),
+        });
+
+        input_request
+    }
+
+    fn dao_policy_result(members: Vec<AccountId>) -> PromiseResult {
+        let policy = serde_json::json!({
+            "roles": [
+                {
+                    "name": "council",
+                    "kind": {
+                        "Group": members
+                    },
+                    "permissions": ["*:*"],
+                    "vote_policy": {}
+                }
+            ]
+        });
+
+        PromiseResult::Successful(policy.to_string().into_bytes())
+    }
+
+    #[should_panic = "ERR_NO_EXECUTORS"]
+    #[test]
+    fn test_register_signature_request_panics_on_empty_dao_role() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = dao_role_request();
+        input_request.allowed_executors = Some(AllowedExecutors::DaoRole {
+            dao_id: dao(),
+            role: "".to_string(),
+        });
+
+        contract.register_signature_request(input_request);
+    }
+
+    #[should_panic = "ERR_INSUFFICIENT_GAS"]
+    #[test]
+    fn test_get_signature_panics_on_insufficient_gas_for_dao_role() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(dao_role_request());
+
+        contract.mpc_signature_deposit = Some(NearToken::from_yoctonear(1));
+
+        // enough for signing, but not for DAO authorization
+        context.prepaid_gas(Gas::from_tgas(270));
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload());
+    }
+
+    #[should_panic = "ERR_MPC_SIGNATURE_DEPOSIT_REQUIRED"]
+    #[test]
+    fn test_get_signature_with_dao_role_panics_without_configured_deposit() {
+        let (mut contract, _) = setup();
+
+        let request = contract.register_signature_request(dao_role_request());
+
+        contract.get_signature(request.request_id, other_payload());
+    }
+
+    #[test]
+    fn test_get_signature_with_dao_role_checks_policy() {
+        let (mut contract, context) = setup();
+
+        let request = contract.register_signature_request(dao_role_request());
+        contract.mpc_signature_deposit = Some(NearToken::from_yoctonear(1));
+
+        // free fees let the whole remaining gas be attached to created promises
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::free(),
+        );
+
+        let _ = contract.get_signature(request.request_id, other_payload());
+
+        let receipts = get_created_receipts();
+        let policy_call = receipts.first().unwrap();
+
+        assert_eq!(policy_call.receiver_id, dao());
+        match &policy_call.actions[..] {
+            [MockAction::FunctionCallWeight { method_name, .. }] => {
+                assert_eq!(method_name, b"get_policy")
+            }
+            _ => panic!("Policy must be requested with a single function call"),
+        }
+    }
+
+    #[test]
+    fn test_on_dao_policy_refunds_non_member() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(dao_role_request());
+
+        context.predecessor_account_id(current());
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![dao_policy_result(vec![user1()])],
+        );
+
+        let _ = contract.on_dao_policy(
+            request.request_id,
+            other_payload(),
+            user2(),
+            NearToken::from_millinear(50),
+        );
+
+        let receipts = get_created_receipts();
+        let refund = receipts.first().unwrap();
+
+        assert_eq!(refund.receiver_id, user2());
+        match &refund.actions[..] {
+            [MockAction::Transfer { deposit, .. }] => {
+                assert_eq!(*deposit, NearToken::from_millinear(50))
+            }
+            _ => panic!("Refund must be a single transfer"),
+        }
+        assert_eq!(
+            contract
+                .get_request(request.request_id)
+                .unwrap()
+                .signature_count,
+            0
+        );
+    }
+
+    #[test]
+    fn test_on_dao_policy_refunds_on_reached_signature_limit() {
+        let (mut contract, mut context) = setup();
+
+        let mut input_request = dao_role_request();
+        input_request.usage_policy = Some(UsagePolicy::OncePerChain);
+        let request = contract.register_signature_request(input_request);
+        contract
+            .requests
+            .get_mut(&request.request_id)
+            .unwrap()
+            .record_signature(other_payload().chain_id);
+
+        context.predecessor_account_id(current());
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![dao_policy_result(vec![user1()])],
+        );
+
+        let _ = contract.on_dao_policy(
+            request.request_id,
+            other_payload(),
+            user1(),
+            NearToken::from_millinear(50),
+        );
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.first().unwrap().receiver_id, user1());
+        assert_eq!(
+            contract
+                .get_request(request.request_id)
+                .unwrap()
+                .signature_count,
+            1
+        );
+    }
+
+    #[test]
+    fn test_on_dao_policy_refunds_on_failed_policy_call() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(dao_role_request());
+
+        context.predecessor_account_id(current());
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+
+        let _ = contract.on_dao_policy(
+            request.request_id,
+            other_payload(),
+            user1(),
+            NearToken::from_millinear(50),
+        );
+
+        let receipts = get_created_receipts();
+        let refund = receipts.first().unwrap();
+
+        assert_eq!(refund.receiver_id, user1());
+    }
+
+    fn dao_proposal() -> DaoProposal {
+        DaoProposal {
+            dao_id: dao(),
+            proposal_id: 7,
+        }
+    }
+
+    fn dao_proposal_result(status: &str, description: String) -> PromiseResult {
+        let proposal = serde_json::json!({
+            "id": 7,
+            "proposer": user1(),
+            "description": description,
+            "kind": "Vote",
+            "status": status,
+            "vote_counts": {},
+            "votes": {},
+            "submission_time": "0"
+        });
+
+        PromiseResult::Successful(proposal.to_string().into_bytes())
+    }
+
+    fn with_proposal_result(context: &mut VMContextBuilder, result: PromiseResult) {
+        context.predecessor_account_id(current());
+        context.attached_deposit(NearToken::from_yoctonear(0));
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_get_request_commitment_is_deterministic() {
+        let (contract, _) = setup();
+
+        let commitment = contract.get_request_commitment(input_request());
+
+        assert!(commitment.starts_with("0x"));
+        assert_eq!(commitment.len(), 66);
+        assert_eq!(commitment, input_request().commitment());
+        assert_ne!(commitment, dao_role_request().commitment());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_EXECUTORS")]
+    fn test_register_approved_signature_request_panics_on_invalid_request() {
+        let (mut contract, _) = setup();
+
+        let mut request = input_request();
+        request.allowed_executors = Some(AllowedExecutors::Accounts(vec![]));
+
+        contract.register_approved_signature_request(request, dao_proposal());
+    }
+
+    #[test]
+    fn test_on_dao_proposal_registers_approved_request() {
+        let (mut contract, mut context) = setup();
+
+        let description = format!("Approve request {}", input_request().commitment());
+        with_proposal_result(&mut context, dao_proposal_result("Approved", description));
+
+        let response = match contract.on_dao_proposal(
+            input_request(),
+            dao_proposal(),
+            user2(),
+            NearToken::from_millinear(10),
+        ) {
+            PromiseOrValue::Value(response) => response,
+            _ => panic!("Request must be registered"),
+        };
+
+        assert_eq!(response.created_by, dao());
+        assert_eq!(response.derivation_path, format!("{}-0", dao()));
+
+        let request = contract.get_request(response.request_id).unwrap();
+        assert!(!request.storage_deposit.is_zero());
+
+        let receipts = get_created_receipts();
+        let refund = receipts.first().unwrap();
+
+        assert_eq!(refund.receiver_id, user2());
+        match &refund.actions[..] {
+            [MockAction::Transfer { deposit, .. }] => assert_eq!(
+                *deposit,
+                NearToken::from_millinear(10).saturating_sub(request.storage_deposit)
+            ),
+            _ => panic!("Refund must be a single transfer"),
+        }
+    }
+
+    #[test]
+    fn test_cancel_approved_request_refunds_storage_payer() {
+        let (mut contract, mut context) = setup();
+
+        let description = format!("Approve request {}", input_request().commitment());
+        with_proposal_result(&mut context, dao_proposal_result("Approved", description));
+
+        let response = match contract.on_dao_proposal(
+            input_request(),
+            dao_proposal(),
+            user2(),
+            NearToken::from_millinear(10),
+        ) {
+            PromiseOrValue::Value(response) => response,
+            _ => panic!("Request must be registered"),
+        };
+
+        let request = contract.get_request(response.request_id).unwrap();
+        assert_eq!(request.created_by, dao());
+        assert_eq!(request.storage_payer_id, user2());
+
+        context.predecessor_account_id(dao());
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.cancel_request(response.request_id);
+
+        let receipts = get_created_receipts();
+        let refund = receipts.last().unwrap();
+
+        assert_eq!(refund.receiver_id, user2());
+        match &refund.actions[..] {
+            [MockAction::Transfer { .. }] => {}
+            _ => panic!("Refund must be a single transfer"),
+        }
+
+        let events = event_data("request_cancelled");
+        assert_eq!(events[0]["created_by"], dao().to_string());
+        assert_eq!(events[0]["refunded_to"], user2().to_string());
+    }
+
+    #[test]
+    fn test_cancel_approved_request_refunds_executors_deposit_to_creator() {
+        let (mut contract, mut context) = setup();
+
+        let description = format!("Approve request {}", input_request().commitment());
+        with_proposal_result(&mut context, dao_proposal_result("Approved", description));
+
+        let response = match contract.on_dao_proposal(
+            input_request(),
+            dao_proposal(),
+            user2(),
+            NearToken::from_millinear(10),
+        ) {
+            PromiseOrValue::Value(response) => response,
+            _ => panic!("Request must be registered"),
+        };
+
+        context.predecessor_account_id(dao());
+        context.attached_deposit(NearToken::from_millinear(10));
+        testing_env!(context.build());
+
+        contract.add_executor(response.request_id, current());
+
+        let request = contract.get_request(response.request_id).unwrap();
+        assert!(!request.executors_storage_deposit.is_zero());
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.cancel_request(response.request_id);
+
+        let refunds: Vec<_> = get_created_receipts()
+            .into_iter()
+            .rev()
+            .take(2)
+            .map(|receipt| match &receipt.actions[..] {
+                [MockAction::Transfer { deposit, .. }] => (receipt.receiver_id.clone(), *deposit),
+                _ => panic!("Refund must be a single transfer"),
+            })
+            .collect();
+
+        assert!(refunds.contains(&(dao(), request.executors_storage_deposit)));
+        assert!(refunds.iter().any(|(account_id, _)| *account_id == user2()));
+
+        let events = event_data("request_cancelled");
+        assert_eq!(events[0]["refunded_to"], user2().to_string());
+        assert_eq!(
+            events[0]["executors_refunded_deposit"],
+            request.executors_storage_deposit.as_yoctonear().to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_ALREADY_USED")]
+    fn test_register_approved_signature_request_panics_on_used_proposal() {
+        let (mut contract, mut context) = setup();
+
+        let description = format!("Approve request {}", input_request().commitment());
+        with_proposal_result(&mut context, dao_proposal_result("Approved", description));
+
+        let _ = contract.on_dao_proposal(
+            input_request(),
+            dao_proposal(),
+            user2(),
+            NearToken::from_millinear(10),
+        );
+
+        contract.register_approved_signature_request(input_request(), dao_proposal());
+    }
+
+    #[test]
+    fn test_on_dao_proposal_refunds_not_approved_proposal() {
+        let (mut contract, mut context) = setup();
+
+        let description = format!("Approve request {}", input_request().commitment());
+        with_proposal_result(&mut context, dao_proposal_result("InProgress", description));
+
+        let result = contract.on_dao_proposal(
+            input_request(),
+            dao_proposal(),
+            user2(),
+            NearToken::from_millinear(10),
+        );
+
+        assert!(matches!(result, PromiseOrValue::Promise(_)));
+        // promises are scheduled once dropped
+        drop(result);
+        assert!(contract.list_requests(None, None, None).is_empty());
+
+        let receipts = get_created_receipts();
+        let refund = receipts.first().unwrap();
+
+        assert_eq!(refund.receiver_id, user2());
+        match &refund.actions[..] {
+            [MockAction::Transfer { deposit, .. }] => {
+                assert_eq!(*deposit, NearToken::from_millinear(10))
+            }
+            _ => panic!("Refund must be a single transfer"),
+        }
+    }
+
+    #[test]
+    fn test_on_dao_proposal_refunds_proposal_for_other_request() {
+        let (mut contract, mut context) = setup();
+
+        let description = format!("Approve request {}", dao_role_request().commitment());
+        with_proposal_result(&mut context, dao_proposal_result("Approved", description));
+
+        let result = contract.on_dao_proposal(
+            input_request(),
+            dao_proposal(),
+            user2(),
+            NearToken::from_millinear(10),
+        );
+
+        assert!(matches!(result, PromiseOrValue::Promise(_)));
+        drop(result);
+        assert!(contract.list_requests(None, None, None).is_empty());
+        assert_eq!(get_created_receipts().first().unwrap().receiver_id, user2());
+    }
+
+    #[test]
+    fn test_on_dao_proposal_refunds_on_insufficient_deposit() {
+        let (mut contract, mut context) = setup();
+
+        let description = format!("Approve request {}", input_request().commitment());
+        with_proposal_result(&mut context, dao_proposal_result("Approved", description));
+
+        let result = contract.on_dao_proposal(
+            input_request(),
+            dao_proposal(),
+            user2(),
+            NearToken::from_yoctonear(1),
+        );
+
+        assert!(matches!(result, PromiseOrValue::Promise(_)));
+        drop(result);
+        assert!(contract.list_requests(None, None, None).is_empty());
+        assert!(!contract.used_proposals.contains(&dao_proposal()));
+    }
+
+    #[test]
+    fn test_cleanup_expired() {
+        let (mut contract, mut context) = setup();
+
+        let request_1 = contract.register_signature_request(input_request());
+        let request_2 = contract.register_signature_request(input_request());
+
+        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        testing_env!(context.build());
+
+        let request_3 = contract.register_signature_request(input_request());
+
+        let storage_deposit = contract
+            .get_request(request_1.request_id)
+            .unwrap()
+            .storage_deposit
+            .saturating_add(
+                contract
+                    .get_request(request_2.request_id)
+                    .unwrap()
+                    .storage_deposit,
+            );
+
+        context.predecessor_account_id(user2());
+        context.attached_deposit(NearToken::from_yoctonear(0));
+        testing_env!(context.build());
+
+        let response = contract.cleanup_expired(None, None);
+
+        assert_eq!(
+            response.removed,
+            vec![request_1.request_id, request_2.request_id]
+        );
+        assert_eq!(response.next_index, None);
+        assert!(contract.get_request(request_1.request_id).is_none());
+        assert!(contract.get_request(request_2.request_id).is_none());
+        assert!(contract.get_request(request_3.request_id).is_some());
+
+        // deposits of both requests are refunded at once
+        let receipts = get_created_receipts();
+        let refund = receipts.last().unwrap();
+
+        assert_eq!(refund.receiver_id, user1());
+        match &refund.actions[..] {
+            [MockAction::Transfer { deposit, .. }] => assert_eq!(*deposit, storage_deposit),
+            _ => panic!("Refund must be a single transfer"),
+        }
+    }
+
+    #[test]
+    fn test_cleanup_expired_respects_limit() {
+        let (mut contract, mut context) = setup();
+
+        for _ in 0..3 {
+            contract.register_signature_request(input_request());
+        }
+
+        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        testing_env!(context.build());
+
+        let response = contract.cleanup_expired(None, Some(2));
+        assert_eq!(response.removed.len(), 2);
+        assert_eq!(response.next_index, Some(0));
+
+        let response = contract.cleanup_expired(response.next_index, Some(2));
+        assert_eq!(response.removed.len(), 1);
+        assert_eq!(response.next_index, None);
+
+        assert!(contract.cleanup_expired(None, Some(2)).removed.is_empty());
+    }
+
+    #[test]
+    fn test_cleanup_expired_bounds_examined_requests() {
+        let (mut contract, mut context) = setup();
+
+        let mut long_lived_request = input_request();
+        long_lived_request.ttl = Some(7 * 24 * 60 * ONE_MINUTE_NANOS);
+
+        for _ in 0..3 {
+            contract.register_signature_request(long_lived_request.clone());
+        }
+        let expired_request = contract.register_signature_request(input_request());
+
+        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        testing_env!(context.build());
+
+        // active requests are examined, but not removed
+        let response = contract.cleanup_expired(None, Some(2));
+        assert!(response.removed.is_empty());
+        assert_eq!(response.next_index, Some(2));
+
+        let response = contract.cleanup_expired(response.next_index, Some(2));
+        assert_eq!(response.removed, vec![expired_request.request_id]);
+        assert_eq!(response.next_index, None);
+        assert_eq!(contract.list_requests(None, None, None).len(), 3);
+    }
+
+    #[test]
+    fn test_cleanup_expired_interleaved_requests_across_pages() {
+        let (mut contract, mut context) = setup();
+
+        let mut long_lived_request = input_request();
+        long_lived_request.ttl = Some(7 * 24 * 60 * ONE_MINUTE_NANOS);
+
+        // the last expired request is moved into the slot of the first one once it's removed,
+        // while the live ones in between are split across two pages
+        let first_expired_request = contract.register_signature_request(input_request());
+        for _ in 0..2 {
+            contract.register_signature_request(long_lived_request.clone());
+        }
+        let last_expired_request = contract.register_signature_request(input_request());
+
+        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        testing_env!(context.build());
+
+        let mut removed = vec![];
+        let mut next_index = None;
+        loop {
+            let response = contract.cleanup_expired(next_index, Some(3));
+            removed.extend(response.removed);
+
+            next_index = response.next_index;
+            if next_index.is_none() {
+                break;
+            }
+        }
+
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                first_expired_request.request_id,
+                last_expired_request.request_id
+            ]
+        );
+
+        let requests = contract.list_requests(None, None, None);
+        assert_eq!(requests.len(), 2);
+        assert!(requests
+            .iter()
+            .all(|request| !request.is_time_exceeded(env::block_timestamp())));
+    }
+
+    #[should_panic = "ERR_NOT_FOUND"]
+    #[test]
+    fn test_get_signature_panics_on_unexisted_request() {
+        let (mut contract, _) = setup();
+
+        let other_payload = other_payload();
+        contract.get_signature(100, other_payload);
+    }
+
+    #[should_panic = "ERR_FORBIDDEN"]
+    #[test]
+    fn test_get_signature_panics_on_non_allowed_actor() {
+        let (mut contract, mut context) = setup();
+
+        let input_request = input_request();
+        let request = contract.register_signature_request(input_request.clone());
+
+        context.predecessor_account_id(user2());
+        testing_env!(context.build());
+
+        let other_payload = other_payload();
+        contract.get_signature(request.request_id, other_payload);
+    }
+
+    #[should_panic = "ERR_TIME_IS_UP"]
+    #[test]
+    fn test_get_signature_panics_on_deadline() {
+        let (mut contract, mut context) = setup();
+
+        let input_request = input_request();
+        let request = contract.register_signature_request(input_request.clone());
+
+        // one day + a second
+        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        testing_env!(context.build());
+
+        let other_payload = other_payload();
+        contract.get_signature(request.request_id, other_payload);
+    }
+
+    #[test]
+    fn test_register_signature_request_with_allowed_chain_ids() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_chain_ids = Some(vec![10, 1, 10]);
+        let response = contract.register_signature_request(input_request);
+
+        let request = contract.get_request(response.request_id).unwrap();
+        assert_eq!(request.allowed_chain_ids, Some(vec![1, 10]));
+        assert!(request.is_chain_allowed(1));
+        assert!(!request.is_chain_allowed(56));
+    }
+
+    #[test]
+    fn test_register_signature_request_with_transaction_type() {
+        let (mut contract, _) = setup();
+
+        let response = contract.register_signature_request(input_request());
+        let request = contract.get_request(response.request_id).unwrap();
+        assert_eq!(request.transaction_type, TransactionType::Eip1559);
+
+        let mut input_request = input_request();
+        input_request.transaction_type = Some(TransactionType::Legacy);
+        let response = contract.register_signature_request(input_request);
+
+        let request = contract.get_request(response.request_id).unwrap();
+        assert_eq!(request.transaction_type, TransactionType::Legacy);
+    }
+
+    #[should_panic = "ERR_NO_CHAIN_IDS"]
+    #[test]
+    fn test_register_signature_request_panics_on_empty_chain_ids() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_chain_ids = Some(vec![]);
+        contract.register_signature_request(input_request);
+    }
+
+    #[should_panic = "ERR_CHAIN_NOT_ALLOWED"]
+    #[test]
+    fn test_get_signature_panics_on_not_allowed_chain() {
+        let (mut contract, mut context) = setup();
+
+        let mut input_request = input_request();
+        input_request.allowed_chain_ids = Some(vec![10]);
+        let request = contract.register_signature_request(input_request);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload());
+    }
+
+    fn gas_fee_limits(max_fee_per_gas: u128) -> GasFeeLimits {
+        GasFeeLimits {
+            max_fee_per_gas: Some(JsonU256::from(max_fee_per_gas)),
+            ..Default::default()
+        }
+    }
+
+    #[should_panic = "ERR_MAX_FEE_PER_GAS_EXCEEDED"]
+    #[test]
+    fn test_get_signature_panics_on_exceeded_gas_fee_limits() {
+        let (mut contract, mut context) = setup();
+
+        let mut input_request = input_request();
+        input_request.gas_fee_limits = Some(gas_fee_limits(100_000));
+        let request = contract.register_signature_request(input_request);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload());
+    }
+
+    #[should_panic = "ERR_MAX_FEE_PER_GAS_EXCEEDED"]
+    #[test]
+    fn test_get_signature_panics_on_exceeded_chain_gas_fee_limits() {
+        let (mut contract, mut context) = setup();
+
+        let mut input_request = input_request();
+        input_request.gas_fee_limits = Some(gas_fee_limits(1_000_000));
+        input_request.chain_gas_fee_limits =
+            Some([(other_payload().chain_id, gas_fee_limits(100_000))].into());
+        let request = contract.register_signature_request(input_request);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload());
+    }
+
+    #[test]
+    fn test_chain_gas_fee_limits_take_precedence() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.gas_fee_limits = Some(gas_fee_limits(100_000));
+        input_request.chain_gas_fee_limits = Some([(1, gas_fee_limits(1_000_000))].into());
+        let response = contract.register_signature_request(input_request);
+
+        let request = contract.get_request(response.request_id).unwrap();
+        assert_eq!(
+            request.gas_fee_limits_for(1),
+            Some(&gas_fee_limits(1_000_000))
+        );
+        assert_eq!(
+            request.gas_fee_limits_for(10),
+            Some(&gas_fee_limits(100_000))
+        );
+    }
+
+    fn register_with_usage_policy(
+        contract: &mut Contract,
+        usage_policy: UsagePolicy,
+    ) -> RegisterSignatureReqResponse {
+        let mut input_request = input_request();
+        input_request.usage_policy = Some(usage_policy);
+
+        contract.register_signature_request(input_request)
+    }
+
+    #[should_panic = "ERR_ALREADY_SIGNED_FOR_CHAIN"]
+    #[test]
+    fn test_get_signature_panics_on_second_signature_for_chain() {
+        let (mut contract, mut context) = setup();
+
+        let request = register_with_usage_policy(&mut contract, UsagePolicy::OncePerChain);
+        contract
+            .requests
+            .get_mut(&request.request_id)
+            .unwrap()
+            .record_signature(other_payload().chain_id);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload());
+    }
+
+    #[should_panic = "ERR_SIGNATURE_LIMIT_REACHED"]
+    #[test]
+    fn test_get_signature_panics_on_reached_signature_limit() {
+        let (mut contract, mut context) = setup();
+
+        let request = register_with_usage_policy(&mut contract, UsagePolicy::MaxSignatures(2));
+        let internal_request = contract.requests.get_mut(&request.request_id).unwrap();
+        internal_request.record_signature(1);
+        internal_request.record_signature(10);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload());
+    }
+
+    #[should_panic = "ERR_INVALID_USAGE_POLICY"]
+    #[test]
+    fn test_register_signature_request_panics_on_zero_signature_limit() {
+        let (mut contract, _) = setup();
+
+        register_with_usage_policy(&mut contract, UsagePolicy::MaxSignatures(0));
+    }
+
+    #[test]
+    fn test_get_request_exposes_signature_counters() {
+        let (mut contract, _) = setup();
+
+        let request = register_with_usage_policy(&mut contract, UsagePolicy::OncePerChain);
+        let internal_request = contract.requests.get_mut(&request.request_id).unwrap();
+        internal_request.record_signature(1);
+        internal_request.record_signature(10);
+
+        let request = contract.get_request(request.request_id).unwrap();
+        assert_eq!(request.usage_policy, UsagePolicy::OncePerChain);
+        assert_eq!(request.signature_count, 2);
+        assert_eq!(request.chain_signature_counts, [(1, 1), (10, 1)].into());
+        assert_eq!(
+            request.usage_error(1),
+            Some(ContractError::AlreadySignedForChain)
+        );
+        assert_eq!(request.usage_error(56), None);
+    }
+
+    #[test]
+    fn test_usage_error_on_too_many_chains() {
+        let (mut contract, _) = setup();
+
+        let request = contract.register_signature_request(input_request());
+        let internal_request = contract.requests.get_mut(&request.request_id).unwrap();
+        for chain_id in 0..MAX_SIGNED_CHAINS as u64 {
+            internal_request.record_signature(chain_id);
+        }
+
+        assert_eq!(
+            internal_request.usage_error(MAX_SIGNED_CHAINS as u64),
+            Some(ContractError::ChainLimitReached)
+        );
+        // chains that were already signed for can still be used
+        assert_eq!(internal_request.usage_error(0), None);
+    }
+
+    fn access_list() -> Option<Vec<AccessListEntry>> {
+        Some(vec![AccessListEntry {
+            address: "0x0000000000000000000000000000000000000001".to_string(),
+            storage_keys: vec![],
+        }])
+    }
+
+    #[should_panic = "ERR_ACCESS_LIST_NOT_ALLOWED"]
+    #[test]
+    fn test_get_signature_panics_on_disallowed_executor_access_list() {
+        let (mut contract, mut context) = setup();
+
+        let mut input_request = input_request();
+        input_request.allow_executor_access_list = Some(false);
+        let request = contract.register_signature_request(input_request);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        let mut other_payload = other_payload();
+        other_payload.access_list = access_list();
+        contract.get_signature(request.request_id, other_payload);
+    }
+
+    #[should_panic = "ERR_ACCESS_LIST_NOT_ALLOWED"]
+    #[test]
+    fn test_get_signature_panics_on_replacing_fixed_access_list() {
+        let (mut contract, mut context) = setup();
+
+        let mut input_request = input_request();
+        input_request.transaction_payload.access_list = access_list();
+        let request = contract.register_signature_request(input_request);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        let mut other_payload = other_payload();
+        other_payload.access_list = access_list();
+        contract.get_signature(request.request_id, other_payload);
+    }
+
+    #[should_panic = "ERR_ACCESS_LIST_NOT_SUPPORTED"]
+    #[test]
+    fn test_register_signature_request_panics_on_legacy_access_list() {
+        let (mut contract, _) = setup();
+
+        let mut input_request = input_request();
+        input_request.transaction_type = Some(TransactionType::Legacy);
+        input_request.transaction_payload.access_list = access_list();
+        contract.register_signature_request(input_request);
+    }
+
+    fn register_with_nonce_override_policy(
+        contract: &mut Contract,
+        nonce_override_policy: NonceOverridePolicy,
+    ) -> RegisterSignatureReqResponse {
+        let mut input_request = input_request();
+        input_request.transaction_payload.nonce = JsonU256::from(5);
+        input_request.nonce_override_policy = Some(nonce_override_policy);
+
+        contract.register_signature_request(input_request)
+    }
+
+    fn other_payload_with_nonce(nonce: u128) -> OtherEip1559TransactionPayload {
+        let mut other_payload = other_payload();
+        other_payload.nonce = Some(JsonU256::from(nonce));
+
+        other_payload
+    }
+
+    #[test]
+    fn test_get_signature_doesnt_track_nonce_until_signed() {
+        let (mut contract, mut context) = setup();
+
+        let request =
+            register_with_nonce_override_policy(&mut contract, NonceOverridePolicy::NotBelowBase);
+
+        context.attached_deposit(NearToken::from_millinear(10));
+        context.predecessor_account_id(user1());
+        // the same nonce can be requested again to replace a pending transaction
+        for _ in 0..2 {
+            // free fees let the whole remaining gas be attached to created promises
+            testing_env!(
+                context.build(),
+                near_sdk::test_vm_config(),
+                near_sdk::RuntimeFeesConfig::free(),
+            );
+
+            let _ = contract.get_signature(request.request_id, other_payload_with_nonce(8));
+        }
+
+        assert_eq!(
+            contract.get_highest_signed_nonce(request.derivation_path, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_on_get_signature_tracks_highest_signed_nonce() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+        produce_eip155_example_signature(&mut contract, &mut context, request.request_id);
+
+        // EIP-155 example transaction has nonce 9
+        assert_eq!(
+            contract.get_highest_signed_nonce(request.derivation_path.clone(), 1),
+            Some(JsonU256::from(9))
+        );
+        assert_eq!(
+            contract.get_highest_signed_nonce(request.derivation_path, 10),
+            None
+        );
+    }
+
+    #[test]
+    fn test_on_get_signature_refunds_unused_storage_deposit() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+        contract.highest_signed_nonces.insert(
+            SignerChainKey {
+                derivation_path: request.derivation_path.clone(),
+                chain_id: 1,
+            },
+            JsonU256::from(3),
+        );
+        contract.highest_signed_nonces.flush();
+
+        let signature = serde_json::json!({
+            "big_r": {
+                "affine_point": "0228EF61340BD939BC2195FE537567866003E1A15D3C71FF63E1590620AA636276"
+            },
+            "s": {
+                "scalar": "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83"
+            },
+            "recovery_id": 0
+        });
+        let result = serde_json::to_vec(&signature).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        contract.on_get_signature(
+            eip155_example_tx_hex(),
+            request.request_id,
+            request.derivation_path.clone(),
+            user2(),
+            NearToken::from_millinear(1),
+            NearToken::from_millinear(10),
+        );
+
+        assert_eq!(
+            contract.get_highest_signed_nonce(request.derivation_path, 1),
+            Some(JsonU256::from(9))
+        );
+
+        // only the history record is charged, the nonce entry already exists
+        let used_storage = SignatureRecord::entry_storage_usage(&user2(), None);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, user2());
+        match &receipts[0].actions[..] {
+            [MockAction::Transfer { deposit, .. }] => assert_eq!(
+                *deposit,
+                NearToken::from_millinear(10)
+                    .saturating_sub(calculate_deposit_for_used_storage(used_storage))
+            ),
+            _ => panic!("Unused storage deposit must be refunded with a single transfer"),
+        }
+    }
+
+    #[test]
+    fn test_on_get_signature_doesnt_track_nonce_on_failed_sign_call() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+        with_promise_result(&mut context, PromiseResult::Failed);
+
+        let result = contract.on_get_signature(
+            eip155_example_tx_hex(),
+            request.request_id,
+            request.derivation_path.clone(),
+            user2(),
+            NearToken::from_millinear(1),
+            NearToken::from_millinear(2),
+        );
+
+        match result {
+            GetSignatureResult::Failed(failure) => {
+                assert_eq!(failure.refunded_deposit, NearToken::from_millinear(3))
+            }
+            GetSignatureResult::Signed(_) => panic!("Signature must fail"),
+        }
+        assert_eq!(
+            contract.get_highest_signed_nonce(request.derivation_path, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_signer_chain_key_entry_storage_usage() {
+        let (mut contract, _) = setup();
+
+        let signer_chain_key = SignerChainKey {
+            derivation_path: "user1-0".to_owned(),
+            chain_id: 11155111,
+        };
+
+        let storage_used_before = env::storage_usage();
+        contract
+            .highest_signed_nonces
+            .insert(signer_chain_key.clone(), JsonU256::from(1));
+        contract.highest_signed_nonces.flush();
+        let storage_used_after = env::storage_usage();
+
+        assert_eq!(
+            storage_used_after - storage_used_before,
+            signer_chain_key.entry_storage_usage()
+        );
+    }
+
+    #[should_panic = "ERR_NONCE_TOO_HIGH"]
+    #[test]
+    fn test_get_signature_panics_on_nonce_too_far_ahead() {
+        let (mut contract, mut context) = setup();
+
+        let request = register_with_nonce_override_policy(&mut contract, NonceOverridePolicy::Any);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        // base nonce of the request is 5
+        contract.get_signature(
+            request.request_id,
+            other_payload_with_nonce(5 + MAX_NONCE_GAP as u128 + 1),
+        );
+    }
+
+    #[test]
+    fn test_get_signature_allows_nonce_within_gap() {
+        let (mut contract, _) = setup();
+
+        let request = register_with_nonce_override_policy(&mut contract, NonceOverridePolicy::Any);
+        let request = contract.get_request(request.request_id).unwrap();
+
+        assert_eq!(
+            contract.nonce_error(
+                &request,
+                &other_payload_with_nonce(5 + MAX_NONCE_GAP as u128)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reset_highest_signed_nonce() {
+        let (mut contract, mut context) = setup();
+
+        let signer_chain_key = SignerChainKey {
+            derivation_path: "user1-0".to_owned(),
+            chain_id: 1,
+        };
+
+        for account_id in [user1(), owner()] {
+            contract
+                .highest_signed_nonces
+                .insert(signer_chain_key.clone(), JsonU256::from(u128::MAX));
+
+            context.predecessor_account_id(account_id);
+            context.attached_deposit(NearToken::from_yoctonear(1));
+            testing_env!(context.build());
+
+            contract.reset_highest_signed_nonce("user1-0".to_owned(), 1);
+
+            assert_eq!(
+                contract.get_highest_signed_nonce("user1-0".to_owned(), 1),
+                None
+            );
+        }
+    }
+
+    #[should_panic = "ERR_FORBIDDEN"]
+    #[test]
+    fn test_reset_highest_signed_nonce_panics_for_other_account() {
+        let (mut contract, mut context) = setup();
+
+        context.predecessor_account_id(user2());
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.reset_highest_signed_nonce("user1-0".to_owned(), 1);
+    }
+
+    #[should_panic = "ERR_NONCE_OVERRIDE_NOT_ALLOWED"]
+    #[test]
+    fn test_get_signature_panics_on_disallowed_nonce_override() {
+        let (mut contract, mut context) = setup();
+
+        let request =
+            register_with_nonce_override_policy(&mut contract, NonceOverridePolicy::Disallowed);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload_with_nonce(8));
+    }
+
+    #[should_panic = "ERR_NONCE_BELOW_BASE"]
+    #[test]
+    fn test_get_signature_panics_on_nonce_below_base() {
+        let (mut contract, mut context) = setup();
+
+        let request =
+            register_with_nonce_override_policy(&mut contract, NonceOverridePolicy::NotBelowBase);
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload_with_nonce(4));
+    }
+
+    #[should_panic = "ERR_STALE_NONCE"]
+    #[test]
+    fn test_get_signature_panics_on_stale_nonce() {
+        let (mut contract, mut context) = setup();
+
+        let request = register_with_nonce_override_policy(&mut contract, NonceOverridePolicy::Any);
+        contract.highest_signed_nonces.insert(
+            SignerChainKey {
+                derivation_path: request.derivation_path,
+                chain_id: 1,
+            },
+            JsonU256::from(10),
+        );
+
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        context.predecessor_account_id(user1());
+        testing_env!(context.build());
+
+        contract.get_signature(request.request_id, other_payload_with_nonce(9));
+    }
+
+    // public key of the private key equal to 1
+    fn mpc_public_key() -> PublicKey {
+        to_near_public_key(&ProjectivePoint::GENERATOR.to_affine())
+    }
+
+    fn with_promise_result(context: &mut VMContextBuilder, result: PromiseResult) {
+        context.predecessor_account_id(current());
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_on_mpc_public_key_caches_public_key() {
+        let (mut contract, mut context) = setup();
+
+        assert!(contract.get_mpc_public_key().is_none());
+        assert!(contract.get_derived_account(user1(), 0).is_none());
+
+        let result = serde_json::to_vec(&mpc_public_key()).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        contract.on_mpc_public_key();
+
+        assert_eq!(contract.get_mpc_public_key(), Some(mpc_public_key()));
+
+        let derived_account = contract.get_derived_account(user1(), 0).unwrap();
+        assert_eq!(derived_account.derivation_path, "user1-0");
+        assert_ne!(derived_account.public_key, mpc_public_key());
+        assert_ne!(
+            derived_account.address,
+            contract.get_derived_account(user1(), 1).unwrap().address
+        );
+    }
+
+    #[should_panic = "ERR_INVALID_MPC_PUBLIC_KEY"]
+    #[test]
+    fn test_on_mpc_public_key_panics_on_ed25519_key() {
+        let (mut contract, mut context) = setup();
+
+        let public_key: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp"
+            .parse()
+            .unwrap();
+        let result = serde_json::to_vec(&public_key).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        contract.on_mpc_public_key();
+    }
+
+    #[test]
+    fn test_register_signature_request_returns_derived_address() {
+        let (mut contract, context) = setup();
+
+        let response = contract.register_signature_request(input_request());
+        assert!(response.derived_address.is_none());
+
+        contract.mpc_public_key = Some(mpc_public_key());
+        testing_env!(context.build());
+
+        let response = contract.register_signature_request(input_request());
+        assert_eq!(
+            response.derived_address,
+            Some(contract.get_derived_account(user1(), 0).unwrap().address)
+        );
+    }
+
+    #[test]
+    fn test_register_signature_request_skips_derived_address_of_other_key_version() {
+        let (mut contract, _) = setup();
+        contract.mpc_public_key = Some(mpc_public_key());
+
+        let mut input_request = input_request();
+        input_request.key_version = Some(1);
+
+        let response = contract.register_signature_request(input_request);
+        assert!(response.derived_address.is_none());
+    }
+
+    #[should_panic = "ERR_INSUFFICIENT_GAS"]
+    #[test]
+    fn test_get_signature_panics_on_insufficient_gas() {
+        let (mut contract, mut context) = setup();
+
+        let input_request = input_request();
+        let request = contract.register_signature_request(input_request.clone());
+
+        // 270TGas - 1Gas
+        context.prepaid_gas(Gas::from_tgas(270).checked_sub(Gas::from_gas(1)).unwrap());
+        testing_env!(context.build());
+
+        let other_payload = other_payload();
+        contract.get_signature(request.request_id, other_payload);
+    }
+
+    // signed transaction example from EIP-155
+    fn eip155_example_tx_hex() -> String {
+        let base_payload = BaseEip1559TransactionPayload {
+            to: Some("0x3535353535353535353535353535353535353535".to_string()),
+            value: Some(JsonU256::from(1_000_000_000_000_000_000)),
+            data: None,
+            nonce: JsonU256::from(9),
+            access_list: None,
+        };
+        let other_payload = OtherEip1559TransactionPayload {
+            gas: Some(JsonU256::from(21_000)),
+            max_fee_per_gas: JsonU256::from(20_000_000_000),
+            max_priority_fee_per_gas: JsonU256::from(0),
+            chain_id: 1,
+            nonce: None,
+            access_list: None,
+        };
+        let tx = create_tx(TransactionType::Legacy, base_payload, other_payload);
+
+        Bytes::from(tx.rlp().to_vec()).to_string()
+    }
+
+    fn call_on_get_signature(contract: &mut Contract, request_id: RequestId) -> GetSignatureResult {
+        contract.on_get_signature(
+            eip155_example_tx_hex(),
+            request_id,
+            "user1-0".to_owned(),
+            user2(),
+            NearToken::from_millinear(10),
+            NearToken::from_yoctonear(0),
+        )
+    }
+
+    #[test]
+    fn test_on_get_signature_returns_signed_tx() {
+        let (mut contract, mut context) = setup();
+
+        let signature = serde_json::json!({
+            "big_r": {
+                "affine_point": "0228EF61340BD939BC2195FE537567866003E1A15D3C71FF63E1590620AA636276"
+            },
+            "s": {
+                "scalar": "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83"
+            },
+            "recovery_id": 0
+        });
+        let result = serde_json::to_vec(&signature).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        let response = match call_on_get_signature(&mut contract, 0) {
+            GetSignatureResult::Signed(response) => response,
+            GetSignatureResult::Failed(_) => panic!("Signature must be produced"),
+        };
+
+        assert_eq!(response.signature.recovery_id, 0);
+        assert_eq!(response.evm_signature.v, 37);
+        assert_eq!(response.evm_signature.y_parity, 0);
+        assert_eq!(
+            response.evm_signature.r,
+            "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276"
+        );
+        assert_eq!(
+            response.signed_tx,
+            "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(
+            response.tx_hash,
+            "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+        );
+        assert_eq!(response.from, "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f");
+    }
+
+    fn expect_signature_failure(result: GetSignatureResult) -> SignatureFailure {
+        match result {
+            GetSignatureResult::Failed(failure) => failure,
+            GetSignatureResult::Signed(_) => panic!("Signature must fail"),
+        }
+    }
+
+    #[test]
+    fn test_on_get_signature_fails_on_high_s() {
+        let (mut contract, mut context) = setup();
+
+        let request = register_with_usage_policy(&mut contract, UsagePolicy::OncePerChain);
+        contract
+            .requests
+            .get_mut(&request.request_id)
+            .unwrap()
+            .record_signature(1);
+
+        let signature = serde_json::json!({
+            "big_r": {
+                "affine_point": "0228EF61340BD939BC2195FE537567866003E1A15D3C71FF63E1590620AA636276"
+            },
+            "s": {
+                "scalar": "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140"
+            },
+            "recovery_id": 0
+        });
+        let result = serde_json::to_vec(&signature).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        let failure = expect_signature_failure(contract.on_get_signature(
+            eip155_example_tx_hex(),
+            request.request_id,
+            request.derivation_path.clone(),
+            user2(),
+            NearToken::from_millinear(10),
+            NearToken::from_millinear(2),
+        ));
+
+        // MPC Contract kept the sign deposit, only the storage deposit is returned
+        assert_eq!(failure.error, ContractError::HighS);
+        assert_eq!(failure.refunded_deposit, NearToken::from_millinear(2));
+
+        // unusable signature doesn't count towards the usage policy
+        let stored_request = contract.requests.get(&request.request_id).unwrap();
+        assert_eq!(stored_request.signature_count, 0);
+        assert_eq!(stored_request.usage_error(1), None);
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, user2());
+    }
+
+    #[test]
+    fn test_on_get_signature_fails_on_malformed_response() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        let result = serde_json::to_vec(&serde_json::json!({ "signature": "0x" })).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        let failure =
+            expect_signature_failure(call_on_get_signature(&mut contract, request.request_id));
+
+        assert_eq!(failure.error, ContractError::CantParseSignature);
+        assert!(failure.refunded_deposit.is_zero());
+        assert!(get_created_receipts().is_empty());
+        assert!(contract
+            .get_signature_history(request.request_id, None, None, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_on_get_signature_fails_on_big_r_off_the_curve() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        let signature = serde_json::json!({
+            "big_r": {
+                "affine_point": format!("02{:064X}", 5)
+            },
+            "s": {
+                "scalar": "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83"
+            },
+            "recovery_id": 0
+        });
+        let result = serde_json::to_vec(&signature).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        let failure =
+            expect_signature_failure(call_on_get_signature(&mut contract, request.request_id));
+
+        assert_eq!(failure.error, ContractError::CantRecoverSigner);
+        assert_eq!(
+            contract.get_highest_signed_nonce(request.derivation_path, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_on_get_signature_refunds_on_failed_sign_call() {
+        let (mut contract, mut context) = setup();
+
+        let request = register_with_usage_policy(&mut contract, UsagePolicy::OncePerChain);
+        // signature for the chain of EIP-155 example was requested
+        contract
+            .requests
+            .get_mut(&request.request_id)
+            .unwrap()
+            .record_signature(1);
+
+        with_promise_result(&mut context, PromiseResult::Failed);
+
+        let failure =
+            expect_signature_failure(call_on_get_signature(&mut contract, request.request_id));
+
+        assert_eq!(failure.request_id, request.request_id);
+        assert_eq!(failure.chain_id, 1);
+        assert_eq!(failure.error, ContractError::SignFailed);
+        assert_eq!(failure.refunded_deposit, NearToken::from_millinear(10));
+
+        // failed signature doesn't count towards the usage policy
+        let stored_request = contract.requests.get(&request.request_id).unwrap();
+        assert_eq!(stored_request.signature_count, 0);
+        assert_eq!(stored_request.usage_error(1), None);
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, user2());
+        match &receipts[0].actions[..] {
+            [MockAction::Transfer { deposit, .. }] => {
+                assert_eq!(*deposit, NearToken::from_millinear(10))
+            }
+            _ => panic!("Deposit must be refunded with a single transfer"),
+        }
+
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        assert!(logs[0].starts_with(
+            r#"EVENT_JSON:{"standard":"abstract_dao","version":"1.0.0","event":"signature_failed","data":{"request_id":"#
+        ));
+    }
+
+    #[test]
+    fn test_set_mpc_signature_deposit() {
+        let (mut contract, mut context) = setup();
+
+        context.predecessor_account_id(owner());
+        context.attached_deposit(NearToken::from_yoctonear(1));
         testing_env!(context.build());
 
-        let other_payload = other_payload();
-        contract.get_signature(request.request_id, other_payload);
+        contract.set_mpc_signature_deposit(Some(NearToken::from_millinear(1)));
+        assert_eq!(
+            contract.get_mpc_signature_deposit(),
+            Some(NearToken::from_millinear(1))
+        );
+
+        contract.set_mpc_signature_deposit(None);
+        assert_eq!(contract.get_mpc_signature_deposit(), None);
     }
 
-    #[should_panic = "ERR_INSUFFICIENT_GAS"]
+    #[should_panic = "ERR_NOT_OWNER"]
     #[test]
-    fn test_get_signature_panics_on_insufficient_gas() {
+    fn test_set_mpc_signature_deposit_panics_on_non_owner() {
         let (mut contract, mut context) = setup();
 
-        let input_request = input_request();
-        let request = contract.register_signature_request(input_request.clone());
+        context.predecessor_account_id(user1());
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.set_mpc_signature_deposit(Some(NearToken::from_millinear(1)));
+    }
+
+    fn signature_receipts(contract: &mut Contract, request_id: RequestId) -> Vec<Receipt> {
+        let mut context = VMContextBuilder::new();
+        context.current_account_id(current());
+        context.account_balance(NearToken::from_near(1));
+        context.attached_deposit(NearToken::from_millinear(10));
+        context.predecessor_account_id(user1());
+        context.prepaid_gas(Gas::from_tgas(300));
+        // free fees let the whole remaining gas be attached to created promises
+        testing_env!(
+            context.build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::free(),
+        );
+
+        drop(contract.get_signature(request_id, other_payload()));
+
+        get_created_receipts()
+    }
+
+    #[test]
+    fn test_get_signature_forwards_configured_deposit() {
+        let (mut contract, _) = setup();
+        contract.mpc_signature_deposit = Some(NearToken::from_millinear(1));
+
+        let request = contract.register_signature_request(input_request());
+
+        let receipts = signature_receipts(&mut contract, request.request_id);
+
+        let refund = receipts
+            .iter()
+            .find(|receipt| receipt.receiver_id == user1())
+            .unwrap();
+        // storage of the highest nonce and the history record is held until the signature is produced
+        let storage_deposit = calculate_deposit_for_used_storage(
+            SignerChainKey {
+                derivation_path: request.derivation_path,
+                chain_id: other_payload().chain_id,
+            }
+            .entry_storage_usage()
+                + SignatureRecord::entry_storage_usage(&user1(), None),
+        );
+        match &refund.actions[..] {
+            [MockAction::Transfer { deposit, .. }] => assert_eq!(
+                *deposit,
+                NearToken::from_millinear(9).saturating_sub(storage_deposit)
+            ),
+            _ => panic!("Excess deposit must be refunded with a single transfer"),
+        }
+
+        let sign_call = receipts
+            .iter()
+            .find(|receipt| receipt.receiver_id == signer())
+            .unwrap();
+        match &sign_call.actions[..] {
+            [MockAction::FunctionCallWeight {
+                method_name,
+                attached_deposit,
+                ..
+            }] => {
+                assert_eq!(method_name, b"sign");
+                assert_eq!(*attached_deposit, NearToken::from_millinear(1));
+            }
+            _ => panic!("Signature must be requested with a single function call"),
+        }
+    }
+
+    #[should_panic = "ERR_INSUFFICIENT_DEPOSIT"]
+    #[test]
+    fn test_get_signature_panics_on_deposit_below_configured() {
+        let (mut contract, _) = setup();
+        contract.mpc_signature_deposit = Some(NearToken::from_near(1));
+
+        let request = contract.register_signature_request(input_request());
+
+        signature_receipts(&mut contract, request.request_id);
+    }
+
+    #[test]
+    fn test_get_signature_queries_deposit_if_not_configured() {
+        let (mut contract, _) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        let receipts = signature_receipts(&mut contract, request.request_id);
+
+        let deposit_call = receipts.first().unwrap();
+        assert_eq!(deposit_call.receiver_id, signer());
+        match &deposit_call.actions[..] {
+            [MockAction::FunctionCallWeight { method_name, .. }] => {
+                assert_eq!(method_name, b"experimental_signature_deposit")
+            }
+            _ => panic!("Deposit must be queried with a single function call"),
+        }
+    }
+
+    #[test]
+    fn test_on_mpc_signature_deposit_refunds_on_insufficient_deposit() {
+        let (mut contract, mut context) = setup();
+
+        let request = register_with_usage_policy(&mut contract, UsagePolicy::OncePerChain);
+        // signature for the chain of EIP-155 example was requested
+        contract
+            .requests
+            .get_mut(&request.request_id)
+            .unwrap()
+            .record_signature(1);
+
+        let result = serde_json::to_vec(&NearToken::from_millinear(20)).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        drop(contract.on_mpc_signature_deposit(
+            eip155_example_tx_hex(),
+            request.request_id,
+            request.derivation_path,
+            user2(),
+            NearToken::from_millinear(10),
+        ));
+
+        let stored_request = contract.requests.get(&request.request_id).unwrap();
+        assert_eq!(stored_request.signature_count, 0);
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, user2());
+        match &receipts[0].actions[..] {
+            [MockAction::Transfer { deposit, .. }] => {
+                assert_eq!(*deposit, NearToken::from_millinear(10))
+            }
+            _ => panic!("Deposit must be refunded with a single transfer"),
+        }
+    }
+
+    // Data of logged NEP-297 events with the given name
+    fn event_data(event: &str) -> Vec<serde_json::Value> {
+        get_logs()
+            .iter()
+            .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+            .map(|log| serde_json::from_str::<serde_json::Value>(log).unwrap())
+            .filter(|log| log["standard"] == "abstract_dao" && log["event"] == event)
+            .map(|log| log["data"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_register_signature_request_emits_event() {
+        let (mut contract, _) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        let events = event_data("request_registered");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["request_id"], request.request_id);
+        assert_eq!(events[0]["derivation_path"], "user1-0");
+        assert_eq!(events[0]["created_by"], user1().to_string());
+    }
+
+    #[test]
+    fn test_get_signature_emits_signature_requested_event() {
+        let (mut contract, _) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        signature_receipts(&mut contract, request.request_id);
+
+        let events = event_data("signature_requested");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["request_id"], request.request_id);
+        assert_eq!(events[0]["chain_id"], other_payload().chain_id);
+        assert_eq!(events[0]["derivation_path"], "user1-0");
+        assert_eq!(events[0]["executor_id"], user1().to_string());
+    }
+
+    #[test]
+    fn test_on_get_signature_emits_signature_produced_event() {
+        let (mut contract, mut context) = setup();
+
+        let signature = serde_json::json!({
+            "big_r": {
+                "affine_point": "0228EF61340BD939BC2195FE537567866003E1A15D3C71FF63E1590620AA636276"
+            },
+            "s": {
+                "scalar": "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83"
+            },
+            "recovery_id": 0
+        });
+        let result = serde_json::to_vec(&signature).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        call_on_get_signature(&mut contract, 0);
+
+        let events = event_data("signature_produced");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["request_id"], 0);
+        assert_eq!(events[0]["chain_id"], 1);
+        assert_eq!(events[0]["derivation_path"], "user1-0");
+        assert_eq!(
+            events[0]["tx_hash"],
+            "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+        );
+    }
+
+    #[test]
+    fn test_on_mpc_signature_deposit_emits_signature_failed_event() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        let result = serde_json::to_vec(&NearToken::from_millinear(20)).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        drop(contract.on_mpc_signature_deposit(
+            eip155_example_tx_hex(),
+            request.request_id,
+            request.derivation_path,
+            user2(),
+            NearToken::from_millinear(10),
+        ));
+
+        let events = event_data("signature_failed");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["request_id"], request.request_id);
+        assert_eq!(events[0]["chain_id"], 1);
+        assert_eq!(events[0]["derivation_path"], "user1-0");
+        assert_eq!(events[0]["error"], "ERR_INSUFFICIENT_DEPOSIT");
+    }
+
+    #[test]
+    fn test_cancel_request_emits_event() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
 
-        // 260TGas - 1Gas
-        context.prepaid_gas(Gas::from_tgas(260).checked_sub(Gas::from_gas(1)).unwrap());
+        context.attached_deposit(NearToken::from_yoctonear(1));
         testing_env!(context.build());
 
-        let other_payload = other_payload();
-        contract.get_signature(request.request_id, other_payload);
+        contract.cancel_request(request.request_id);
+
+        let events = event_data("request_cancelled");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["request_id"], request.request_id);
+        assert_eq!(events[0]["derivation_path"], "user1-0");
+        assert_eq!(events[0]["created_by"], user1().to_string());
+    }
+
+    #[test]
+    fn test_cleanup_expired_emits_event_per_request() {
+        let (mut contract, mut context) = setup();
+
+        let request_1 = contract.register_signature_request(input_request());
+        let request_2 = contract.register_signature_request(input_request());
+
+        context.block_timestamp(24 * 60 * ONE_MINUTE_NANOS + 1);
+        testing_env!(context.build());
+
+        contract.cleanup_expired(None, None);
+
+        let events = event_data("request_expired_cleanup");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["request_id"], request_1.request_id);
+        assert_eq!(events[1]["request_id"], request_2.request_id);
+        assert_eq!(events[0]["derivation_path"], "user1-0");
+    }
+
+    fn produce_eip155_example_signature(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        request_id: RequestId,
+    ) {
+        let signature = serde_json::json!({
+            "big_r": {
+                "affine_point": "0228EF61340BD939BC2195FE537567866003E1A15D3C71FF63E1590620AA636276"
+            },
+            "s": {
+                "scalar": "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83"
+            },
+            "recovery_id": 0
+        });
+        let result = serde_json::to_vec(&signature).unwrap();
+        with_promise_result(context, PromiseResult::Successful(result));
+
+        call_on_get_signature(contract, request_id);
+    }
+
+    #[test]
+    fn test_on_get_signature_records_history() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        context.block_timestamp(ONE_MINUTE_NANOS);
+        produce_eip155_example_signature(&mut contract, &mut context, request.request_id);
+
+        let history = contract.get_signature_history(request.request_id, None, None, None);
+        assert_eq!(history.len(), 1);
+
+        let record = &history[0];
+        assert_eq!(record.chain_id, 1);
+        assert_eq!(
+            record.tx_hash,
+            "0x33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+        );
+        assert_eq!(record.nonce, JsonU256::from(9));
+        assert_eq!(record.gas, JsonU256::from(21_000));
+        assert_eq!(record.max_fee_per_gas, JsonU256::from(20_000_000_000));
+        assert_eq!(record.max_priority_fee_per_gas, None);
+        assert_eq!(record.access_list, None);
+        assert_eq!(record.signature.v, 37);
+        assert_eq!(
+            record.signature.r,
+            "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276"
+        );
+        assert_eq!(
+            record.signature.s,
+            "0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(record.executor_id, user2());
+        assert_eq!(record.signed_at, ONE_MINUTE_NANOS);
+    }
+
+    #[test]
+    fn test_on_get_signature_records_access_list() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        let access_list = vec![AccessListEntry {
+            address: format!("0x{}", "33".repeat(20)),
+            storage_keys: vec![format!("0x{}", "44".repeat(32))],
+        }];
+        let tx = create_tx(
+            TransactionType::Eip1559,
+            BaseEip1559TransactionPayload {
+                to: Some("0x3535353535353535353535353535353535353535".to_string()),
+                value: None,
+                data: None,
+                nonce: JsonU256::from(9),
+                access_list: None,
+            },
+            OtherEip1559TransactionPayload {
+                gas: None,
+                max_fee_per_gas: JsonU256::from(20_000_000_000),
+                max_priority_fee_per_gas: JsonU256::from(1_000_000_000),
+                chain_id: 1,
+                nonce: None,
+                access_list: Some(access_list.clone()),
+            },
+        );
+
+        let signature = serde_json::json!({
+            "big_r": {
+                "affine_point": "0228EF61340BD939BC2195FE537567866003E1A15D3C71FF63E1590620AA636276"
+            },
+            "s": {
+                "scalar": "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83"
+            },
+            "recovery_id": 0
+        });
+        let result = serde_json::to_vec(&signature).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        contract.on_get_signature(
+            Bytes::from(tx.rlp().to_vec()).to_string(),
+            request.request_id,
+            request.derivation_path.clone(),
+            user2(),
+            NearToken::from_millinear(1),
+            NearToken::from_millinear(10),
+        );
+
+        let history = contract.get_signature_history(request.request_id, None, None, None);
+        assert_eq!(history[0].access_list, Some(access_list));
+    }
+
+    #[test]
+    fn test_signature_history_is_bounded_and_paginated() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        for minute in 0..MAX_SIGNATURE_HISTORY as u64 + 2 {
+            context.block_timestamp(minute * ONE_MINUTE_NANOS);
+            produce_eip155_example_signature(&mut contract, &mut context, request.request_id);
+        }
+
+        let history = contract.get_signature_history(request.request_id, None, None, None);
+        assert_eq!(history.len(), MAX_SIGNATURE_HISTORY as usize);
+        // the oldest records are dropped first
+        assert_eq!(history[0].signed_at, 2 * ONE_MINUTE_NANOS);
+
+        let page = contract.get_signature_history(request.request_id, Some(1), Some(8), Some(5));
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].signed_at, 10 * ONE_MINUTE_NANOS);
+
+        assert!(contract
+            .get_signature_history(request.request_id, Some(10), None, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_signature_history_is_removed_with_request() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+        produce_eip155_example_signature(&mut contract, &mut context, request.request_id);
+
+        context.predecessor_account_id(user1());
+        context.attached_deposit(NearToken::from_yoctonear(1));
+        testing_env!(context.build());
+
+        contract.cancel_request(request.request_id);
+
+        assert!(contract
+            .get_signature_history(request.request_id, None, None, None)
+            .is_empty());
+
+        // the executor gets the storage deposit of its record back
+        let history_deposit = calculate_deposit_for_used_storage(
+            SignatureRecord::entry_storage_usage(&user2(), None),
+        );
+        let history_refund = get_created_receipts()
+            .into_iter()
+            .find(|receipt| receipt.receiver_id == user2())
+            .unwrap();
+        match &history_refund.actions[..] {
+            [MockAction::Transfer { deposit, .. }] => assert_eq!(*deposit, history_deposit),
+            _ => panic!("Refund must be a single transfer"),
+        }
+
+        let events = event_data("request_cancelled");
+        assert_eq!(
+            events[0]["history_refunded_deposit"],
+            history_deposit.as_yoctonear().to_string()
+        );
+        assert!(contract
+            .signature_history
+            .get(&SignatureRecordKey {
+                request_id: request.request_id,
+                slot: 0,
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn test_signature_history_isnt_recorded_for_removed_request() {
+        let (mut contract, mut context) = setup();
+
+        produce_eip155_example_signature(&mut contract, &mut context, 0);
+
+        assert!(contract
+            .signature_history
+            .get(&SignatureRecordKey {
+                request_id: 0,
+                slot: 0,
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn test_signature_record_entry_storage_usage() {
+        let (mut contract, _) = setup();
+
+        let record = SignatureRecord {
+            chain_id: 11155111,
+            tx_hash: format!("0x{}", "00".repeat(32)),
+            nonce: JsonU256::from(9),
+            gas: JsonU256::from(21_000),
+            max_fee_per_gas: JsonU256::from(20_000_000_000),
+            max_priority_fee_per_gas: Some(JsonU256::from(1_000_000_000)),
+            access_list: Some(vec![AccessListEntry {
+                address: format!("0x{}", "33".repeat(20)),
+                storage_keys: vec![format!("0x{}", "44".repeat(32))],
+            }]),
+            signature: EvmSignature {
+                r: format!("0x{}", "11".repeat(32)),
+                s: format!("0x{}", "22".repeat(32)),
+                v: 0,
+                y_parity: 0,
+            },
+            executor_id: user2(),
+            signed_at: 0,
+        };
+
+        let storage_usage = record.storage_usage();
+
+        let storage_used_before = env::storage_usage();
+        contract.signature_history.insert(
+            SignatureRecordKey {
+                request_id: 0,
+                slot: 0,
+            },
+            record,
+        );
+        contract.signature_history.flush();
+        let storage_used_after = env::storage_usage();
+
+        assert_eq!(storage_used_after - storage_used_before, storage_usage);
+    }
+
+    #[test]
+    fn test_signature_history_overwrite_refunds_previous_executor() {
+        let (mut contract, mut context) = setup();
+
+        let request = contract.register_signature_request(input_request());
+
+        for _ in 0..MAX_SIGNATURE_HISTORY {
+            produce_eip155_example_signature(&mut contract, &mut context, request.request_id);
+        }
+
+        let signature = serde_json::json!({
+            "big_r": {
+                "affine_point": "0228EF61340BD939BC2195FE537567866003E1A15D3C71FF63E1590620AA636276"
+            },
+            "s": {
+                "scalar": "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83"
+            },
+            "recovery_id": 0
+        });
+        let result = serde_json::to_vec(&signature).unwrap();
+        with_promise_result(&mut context, PromiseResult::Successful(result));
+
+        // the longer executor id is charged in full even though the slot is overwritten
+        let storage_deposit =
+            calculate_deposit_for_used_storage(SignatureRecord::entry_storage_usage(&dao(), None));
+        contract.on_get_signature(
+            eip155_example_tx_hex(),
+            request.request_id,
+            request.derivation_path.clone(),
+            dao(),
+            NearToken::from_millinear(1),
+            storage_deposit,
+        );
+
+        let refunds: Vec<_> = get_created_receipts()
+            .into_iter()
+            .map(|receipt| match &receipt.actions[..] {
+                [MockAction::Transfer { deposit, .. }] => (receipt.receiver_id.clone(), *deposit),
+                _ => panic!("Refund must be a single transfer"),
+            })
+            .collect();
+
+        assert_eq!(
+            refunds,
+            vec![(
+                user2(),
+                calculate_deposit_for_used_storage(SignatureRecord::entry_storage_usage(
+                    &user2(),
+                    None
+                ))
+            )]
+        );
+        assert_eq!(
+            contract
+                .get_signature_history(request.request_id, None, Some(9), None)
+                .first()
+                .unwrap()
+                .executor_id,
+            dao()
+        );
     }
 }
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+
+use near_sdk::json_types::U128;
+use near_sdk::store::{IterableMap, LookupMap, LookupSet};
+use near_sdk::{env, near, AccountId, NearToken, Timestamp};
+
+use crate::constants::{DEFAULT_MAX_REQUEST_TTL, DEFAULT_MIN_REQUEST_TTL};
+use crate::errors::{ContractError, UnwrapOrPanic};
+use crate::helpers::calculate_deposit_for_used_storage;
+use crate::primitives::{
+    AllowedExecutors, BaseEip1559TransactionPayload, NonceOverridePolicy, Request, RequestId,
+    RequestTtlLimits, StorageKey, TransactionType, UsagePolicy,
+};
+use crate::Contract;
+
+/// Legacy transaction payload layout, stored before requests became enumerable
+#[near(serializers = [borsh])]
+pub struct BaseEip1559TransactionPayloadV0 {
+    pub to: String,
+    pub data: Option<String>,
+    pub value: Option<U128>,
+    pub nonce: U128,
+}
+
+impl From<BaseEip1559TransactionPayloadV0> for BaseEip1559TransactionPayload {
+    fn from(payload: BaseEip1559TransactionPayloadV0) -> Self {
+        Self {
+            to: Some(payload.to),
+            data: payload.data,
+            value: payload.value.map(|value| value.0.into()),
+            nonce: payload.nonce.0.into(),
+            access_list: None,
+        }
+    }
+}
+
+/// Legacy request layout, stored before requests became enumerable
+#[near(serializers = [borsh])]
+pub struct RequestV0 {
+    pub id: RequestId,
+    pub allowed_account_id: AccountId,
+    pub deadline: Timestamp,
+    pub payload: BaseEip1559TransactionPayloadV0,
+    pub derivation_path: String,
+    pub key_version: u32,
+}
+
+impl RequestV0 {
+    // Derivation path has "{predecessor}-{seed}" format
+    fn creator_id(&self) -> Option<AccountId> {
+        let (creator_id, _) = self.derivation_path.rsplit_once('-')?;
+
+        creator_id.parse().ok()
+    }
+
+    // A creator that can't be parsed falls back to the contract owner, so a single malformed
+    // request doesn't block the migration and can still be cancelled
+    fn into_request(self, fallback_creator_id: &AccountId) -> Request {
+        let creator_id = self.creator_id().unwrap_or_else(|| {
+            env::log_str(&format!(
+                "Request {} has malformed derivation path, its creator is set to {}",
+                self.id, fallback_creator_id
+            ));
+
+            fallback_creator_id.clone()
+        });
+
+        Request {
+            id: self.id,
+            created_by: creator_id.clone(),
+            // wasn't recorded in the legacy layout
+            created_at: 0,
+            created_at_block: 0,
+            allowed_executors: AllowedExecutors::Accounts(vec![self.allowed_account_id]),
+            deadline: self.deadline,
+            payload: self.payload.into(),
+            derivation_path: self.derivation_path,
+            key_version: self.key_version,
+            signature_count: 0,
+            recorded_signature_count: 0,
+            // set once the request is written in the new layout
+            storage_deposit: NearToken::from_yoctonear(0),
+            storage_payer_id: creator_id,
+            executors_storage_deposit: NearToken::from_yoctonear(0),
+            allowed_chain_ids: None,
+            gas_fee_limits: None,
+            chain_gas_fee_limits: None,
+            usage_policy: UsagePolicy::Unlimited,
+            chain_signature_counts: BTreeMap::new(),
+            transaction_type: TransactionType::Eip1559,
+            // legacy requests signed the payload as registered, so executors can't extend it
+            allow_executor_access_list: false,
+            nonce_override_policy: NonceOverridePolicy::Disallowed,
+        }
+    }
+}
+
+/// Range of request ids that may still be stored in the legacy layout
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[near(serializers = [borsh, json])]
+pub struct RequestsMigration {
+    // Id of the next request to be moved
+    pub next_id: RequestId,
+    // Requests registered after the upgrade start from this id
+    pub end_id: RequestId,
+}
+
+/// Legacy contract state layout
+#[near(serializers = [borsh])]
+pub struct ContractV0 {
+    pub next_request_id: RequestId,
+    pub requests: LookupMap<RequestId, RequestV0>,
+    pub mpc_contract_id: AccountId,
+}
+
+impl ContractV0 {
+    // Requests are moved in batches by migrate_requests(), so the upgrade itself fits in gas
+    pub fn migrate(self, owner_id: AccountId) -> Contract {
+        Contract {
+            next_request_id: self.next_request_id,
+            requests: IterableMap::new(StorageKey::Requests),
+            requests_by_creator: LookupMap::new(StorageKey::RequestsByCreator),
+            requests_by_executor: LookupMap::new(StorageKey::RequestsByExecutor),
+            cancelled_requests: LookupSet::new(StorageKey::CancelledRequests),
+            used_proposals: LookupSet::new(StorageKey::UsedProposals),
+            highest_signed_nonces: LookupMap::new(StorageKey::HighestSignedNonces),
+            signature_history: LookupMap::new(StorageKey::SignatureHistory),
+            mpc_contract_id: self.mpc_contract_id,
+            mpc_public_key: None,
+            mpc_signature_deposit: None,
+            owner_id,
+            request_ttl_limits: RequestTtlLimits {
+                min: DEFAULT_MIN_REQUEST_TTL,
+                max: DEFAULT_MAX_REQUEST_TTL,
+            },
+            requests_migration: (self.next_request_id > 0).then_some(RequestsMigration {
+                next_id: 0,
+                end_id: self.next_request_id,
+            }),
+        }
+    }
+}
+
+impl Contract {
+    // Examines up to `limit` ids, so every call is bounded regardless of the amount of requests
+    pub(crate) fn migrate_legacy_requests(&mut self, limit: u64) {
+        let Some(migration) = self.requests_migration else {
+            return;
+        };
+
+        let mut legacy_requests: LookupMap<RequestId, RequestV0> =
+            LookupMap::new(StorageKey::AllRequests);
+
+        let next_id = migration
+            .end_id
+            .min(migration.next_id.saturating_add(limit));
+
+        // LookupMap isn't iterable, but ids were assigned sequentially
+        for request_id in migration.next_id..next_id {
+            if let Some(request) = legacy_requests.remove(&request_id) {
+                let storage_used_before = env::storage_usage();
+                let request = request.into_request(&self.owner_id);
+                self.insert_request(request);
+                let storage_used_after = env::storage_usage();
+
+                let storage_deposit = calculate_deposit_for_used_storage(
+                    storage_used_after
+                        .checked_sub(storage_used_before)
+                        .unwrap_or_panic(ContractError::Unexpected),
+                );
+
+                if let Some(request) = self.requests.get_mut(&request_id) {
+                    request.storage_deposit = storage_deposit;
+                }
+            }
+        }
+        legacy_requests.flush();
+
+        self.requests_migration = (next_id < migration.end_id).then_some(RequestsMigration {
+            next_id,
+            end_id: migration.end_id,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use near_sdk::{env, test_utils::VMContextBuilder, testing_env};
+
+    use crate::primitives::JsonU256;
+
+    use super::*;
+
+    fn legacy_request(id: RequestId, derivation_path: &str) -> RequestV0 {
+        RequestV0 {
+            id,
+            allowed_account_id: AccountId::from_str("executor").unwrap(),
+            deadline: 100,
+            payload: BaseEip1559TransactionPayloadV0 {
+                to: "0x0000000000000000000000000000000000000000".to_string(),
+                data: None,
+                value: Some(U128(1_000)),
+                nonce: U128(7),
+            },
+            derivation_path: derivation_path.to_string(),
+            key_version: 0,
+        }
+    }
+
+    #[test]
+    fn test_migrate_from_v0() {
+        let context = VMContextBuilder::new();
+        testing_env!(context.build());
+
+        let mut requests = LookupMap::new(StorageKey::AllRequests);
+        requests.insert(0, legacy_request(0, "dao.near-0"));
+        requests.insert(1, legacy_request(1, "other-dao.near-5"));
+        requests.flush();
+
+        env::state_write(&ContractV0 {
+            next_request_id: 2,
+            requests,
+            mpc_contract_id: AccountId::from_str("signer").unwrap(),
+        });
+
+        let mut contract = Contract::migrate(Some(AccountId::from_str("owner").unwrap()));
+
+        assert_eq!(
+            contract.get_requests_migration(),
+            Some(RequestsMigration {
+                next_id: 0,
+                end_id: 2
+            })
+        );
+        assert_eq!(contract.migrate_requests(None), None);
+
+        assert_eq!(contract.next_request_id, 2);
+        assert_eq!(contract.get_mpc_contract_id().as_str(), "signer");
+        assert_eq!(contract.get_owner_id().as_str(), "owner");
+
+        let requests = contract.list_requests(None, None, None);
+        assert_eq!(requests.len(), 2);
+
+        let request = contract.get_request(1).unwrap();
+        assert_eq!(request.created_by.as_str(), "other-dao.near");
+        assert_eq!(
+            request.allowed_executors,
+            AllowedExecutors::Accounts(vec![AccountId::from_str("executor").unwrap()])
+        );
+        assert_eq!(request.created_at, 0);
+        assert_eq!(request.payload.value, Some(JsonU256::from(1_000)));
+        assert_eq!(request.payload.nonce, JsonU256::from(7));
+        assert!(!request.storage_deposit.is_zero());
+        assert!(!request.allow_executor_access_list);
+
+        let dao_requests = contract.list_requests_by_creator(
+            AccountId::from_str("dao.near").unwrap(),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(dao_requests.len(), 1);
+        assert_eq!(dao_requests[0].id, 0);
+
+        let other_dao_requests = contract.list_requests_by_creator(
+            AccountId::from_str("other-dao.near").unwrap(),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(other_dao_requests.len(), 1);
+        assert_eq!(other_dao_requests[0].id, 1);
+
+        let executor_requests = contract.list_requests_for_executor(
+            AccountId::from_str("executor").unwrap(),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(executor_requests.len(), 2);
+    }
+
+    #[test]
+    fn test_migrate_requests_in_batches() {
+        let context = VMContextBuilder::new();
+        testing_env!(context.build());
+
+        let mut requests = LookupMap::new(StorageKey::AllRequests);
+        // requests with ids 1 and 3 were removed before the upgrade
+        for request_id in [0, 2, 4] {
+            requests.insert(request_id, legacy_request(request_id, "dao.near-0"));
+        }
+        requests.flush();
+
+        env::state_write(&ContractV0 {
+            next_request_id: 5,
+            requests,
+            mpc_contract_id: AccountId::from_str("signer").unwrap(),
+        });
+
+        let mut contract = Contract::migrate(None);
+
+        assert_eq!(
+            contract.migrate_requests(Some(2)),
+            Some(RequestsMigration {
+                next_id: 2,
+                end_id: 5
+            })
+        );
+        assert_eq!(contract.list_requests(None, None, None).len(), 1);
+
+        assert_eq!(
+            contract.migrate_requests(Some(2)),
+            Some(RequestsMigration {
+                next_id: 4,
+                end_id: 5
+            })
+        );
+        assert_eq!(contract.migrate_requests(Some(2)), None);
+        assert_eq!(contract.list_requests(None, None, None).len(), 3);
+
+        // nothing is left to migrate
+        assert_eq!(contract.migrate_requests(Some(2)), None);
+        assert_eq!(contract.list_requests(None, None, None).len(), 3);
+    }
+
+    #[test]
+    fn test_migrate_request_with_malformed_derivation_path() {
+        let context = VMContextBuilder::new();
+        testing_env!(context.build());
+
+        let mut requests = LookupMap::new(StorageKey::AllRequests);
+        requests.insert(0, legacy_request(0, "malformed"));
+        requests.insert(1, legacy_request(1, "dao.near-0"));
+        requests.flush();
+
+        env::state_write(&ContractV0 {
+            next_request_id: 2,
+            requests,
+            mpc_contract_id: AccountId::from_str("signer").unwrap(),
+        });
+
+        let mut contract = Contract::migrate(Some(AccountId::from_str("owner").unwrap()));
+
+        assert_eq!(contract.migrate_requests(None), None);
+
+        let request = contract.get_request(0).unwrap();
+        assert_eq!(request.created_by.as_str(), "owner");
+        assert_eq!(request.storage_payer_id.as_str(), "owner");
+        assert_eq!(
+            contract.get_request(1).unwrap().created_by.as_str(),
+            "dao.near"
+        );
+    }
+
+    #[test]
+    fn test_migrate_without_requests() {
+        let context = VMContextBuilder::new();
+        testing_env!(context.build());
+
+        env::state_write(&ContractV0 {
+            next_request_id: 0,
+            requests: LookupMap::new(StorageKey::AllRequests),
+            mpc_contract_id: AccountId::from_str("signer").unwrap(),
+        });
+
+        let contract = Contract::migrate(None);
+
+        assert_eq!(contract.get_requests_migration(), None);
+    }
+}
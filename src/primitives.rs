@@ -1,20 +1,86 @@
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
 use ethers_contract::encode_function_data;
-use ethers_core::abi::{Function, Token, Tokenize};
-use ethers_core::types::{Bytes, Eip1559TransactionRequest, NameOrAddress, H160};
+use ethers_core::abi::{Constructor, Function, Token, Tokenize};
+use ethers_core::types::transaction::eip2930::{AccessList, AccessListItem};
+use ethers_core::types::{Bytes, Eip1559TransactionRequest, NameOrAddress, H160, H256, U256};
+use ethers_core::utils::keccak256;
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::json_types::U128;
-use near_sdk::{AccountId, BorshStorageKey, Timestamp};
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use near_sdk::{
+    AccountId, BlockHeight, BorshStorageKey, CryptoHash, Duration, FunctionError, NearToken,
+    PublicKey, StorageUsage, Timestamp,
+};
+
+use crate::constants::{
+    DEFAULT_TX_GAS, MAX_REQUEST_TTL_LIMIT, MAX_SIGNED_CHAINS, STORAGE_RECORD_OVERHEAD,
+};
+use crate::errors::{ensure, ContractError, UnwrapOrPanic};
 
 #[derive(BorshSerialize, BorshDeserialize, BorshStorageKey)]
 #[borsh(crate = "near_sdk::borsh")]
 pub enum StorageKey {
+    // Prefix of the LookupMap that stored requests before they became enumerable
+    // Only used during state migration
     AllRequests,
+    Requests,
+    RequestsByCreator,
+    RequestsByCreatorInner { account_id_hash: CryptoHash },
+    RequestsByExecutor,
+    RequestsByExecutorInner { account_id_hash: CryptoHash },
+    CancelledRequests,
+    UsedProposals,
+    HighestSignedNonces,
+    SignatureHistory,
 }
 
 pub type RequestId = u64;
 
+/// 256-bit integer that is serialized to JSON as a decimal string
+/// Both decimal and 0x-prefixed hex strings are accepted during deserialization
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub struct JsonU256(pub U256);
+
+impl From<u128> for JsonU256 {
+    fn from(value: u128) -> Self {
+        Self(U256::from(value))
+    }
+}
+
+impl Serialize for JsonU256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonU256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <String as Deserialize>::deserialize(deserializer)?;
+
+        let parsed = match value.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+            None => U256::from_dec_str(&value).ok(),
+        };
+
+        parsed
+            .map(Self)
+            .ok_or_else(|| de::Error::custom(ContractError::CantParseU256.code()))
+    }
+}
+
+impl BorshSerialize for JsonU256 {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        BorshSerialize::serialize(&self.0 .0, writer)
+    }
+}
+
+impl BorshDeserialize for JsonU256 {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        Ok(Self(U256(<[u64; 4]>::deserialize_reader(reader)?)))
+    }
+}
+
 #[derive(Clone)]
 #[near_sdk::near(serializers = [json])]
 pub struct FunctionData {
@@ -33,7 +99,40 @@ impl Tokenize for FunctionData {
 impl FunctionData {
     pub fn encode(&self) -> Bytes {
         encode_function_data(&self.function_abi, self.clone())
-            .expect("Function arguments don't match provided ABI")
+            .unwrap_or_panic(ContractError::InvalidFunctionArguments)
+    }
+}
+
+#[derive(Clone)]
+#[near_sdk::near(serializers = [json])]
+pub struct DeploymentData {
+    // Hex-encoded init bytecode of the contract
+    pub bytecode: String,
+    // Constructor ABI that is used for arguments encoding
+    // Can be omitted if the constructor doesn't take arguments
+    pub constructor_abi: Option<Constructor>,
+    // Arguments to provide for constructor from ABI
+    pub arguments: Vec<Token>,
+}
+
+impl DeploymentData {
+    pub fn encode(&self) -> Bytes {
+        let bytecode =
+            Bytes::from_str(&self.bytecode).unwrap_or_panic(ContractError::CantParseBytecode);
+
+        match &self.constructor_abi {
+            Some(constructor_abi) => constructor_abi
+                .encode_input(bytecode.to_vec(), &self.arguments)
+                .unwrap_or_panic(ContractError::InvalidConstructorArguments)
+                .into(),
+            None => {
+                ensure(
+                    self.arguments.is_empty(),
+                    ContractError::InvalidConstructorArguments,
+                );
+                bytecode
+            }
+        }
     }
 }
 
@@ -42,35 +141,102 @@ impl FunctionData {
 pub struct InputTransactionPayload {
     // Stringified function arguments together with ABI
     pub function_data: Option<FunctionData>,
-    // Receiver address
-    pub to: String,
-    // Stringified wei value
-    pub value: Option<U128>,
-    // Stringified nonce
-    pub nonce: U128,
+    // Init bytecode and constructor arguments, only for contract deployment
+    pub deployment_data: Option<DeploymentData>,
+    // Receiver address, must be omitted for contract deployment
+    pub to: Option<String>,
+    // Stringified wei value, decimal or 0x-prefixed hex
+    pub value: Option<JsonU256>,
+    // Stringified nonce, decimal or 0x-prefixed hex
+    pub nonce: JsonU256,
+    // Addresses and storage keys the transaction plans to access
+    pub access_list: Option<Vec<AccessListEntry>>,
 }
 
 impl From<InputTransactionPayload> for BaseEip1559TransactionPayload {
     fn from(input: InputTransactionPayload) -> Self {
-        let valid_address = H160::from_str(&input.to).expect("ERR_CANT_PARSE_ADDRESS");
+        // deployment doesn't have a receiver and can't call a function
+        ensure(
+            match input.to {
+                Some(_) => input.deployment_data.is_none(),
+                None => input.deployment_data.is_some() && input.function_data.is_none(),
+            },
+            ContractError::InvalidDeployment,
+        );
+
+        let valid_address = input
+            .to
+            .map(|to| H160::from_str(&to).unwrap_or_panic(ContractError::CantParseAddress));
+        let data = match input.deployment_data {
+            Some(deployment_data) => Some(deployment_data.encode()),
+            None => input.function_data.map(|data| data.encode()),
+        };
 
         Self {
-            to: Bytes::from(valid_address.0).to_string(),
+            to: valid_address.map(|address| Bytes::from(address.0).to_string()),
             nonce: input.nonce,
             value: input.value,
-            data: match input.function_data {
-                Some(data) => Some(data.encode().to_string()),
-                None => None,
-            },
+            data: data.map(|data| data.to_string()),
+            access_list: input.access_list.map(|access_list| {
+                to_access_list(access_list)
+                    .0
+                    .into_iter()
+                    .map(Into::into)
+                    .collect()
+            }),
         }
     }
 }
 
+/// Item of EIP-2930 access list with hex-encoded address and storage keys
+#[derive(Clone, PartialEq, Debug)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub struct AccessListEntry {
+    pub address: String,
+    pub storage_keys: Vec<String>,
+}
+
+impl From<AccessListEntry> for AccessListItem {
+    fn from(entry: AccessListEntry) -> Self {
+        Self {
+            address: H160::from_str(&entry.address)
+                .unwrap_or_panic(ContractError::CantParseAccessList),
+            storage_keys: entry
+                .storage_keys
+                .iter()
+                .map(|key| H256::from_str(key).unwrap_or_panic(ContractError::CantParseAccessList))
+                .collect(),
+        }
+    }
+}
+
+impl From<AccessListItem> for AccessListEntry {
+    fn from(item: AccessListItem) -> Self {
+        Self {
+            address: Bytes::from(item.address.0).to_string(),
+            storage_keys: item
+                .storage_keys
+                .iter()
+                .map(|key| Bytes::from(key.0).to_string())
+                .collect(),
+        }
+    }
+}
+
+pub fn to_access_list(entries: Vec<AccessListEntry>) -> AccessList {
+    AccessList(entries.into_iter().map(Into::into).collect())
+}
+
 #[derive(Clone)]
 #[near_sdk::near(serializers = [json])]
 pub struct InputRequest {
-    // The account ID that is authorized to call get_signature()
-    pub allowed_account_id: AccountId,
+    // Accounts that are authorized to call get_signature()
+    // Either this or allowed_account_id must be provided
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_executors: Option<AllowedExecutors>,
+    // Single executor, the layout accepted up to v0.6.x
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_account_id: Option<AccountId>,
     // Stringified, raw Ethereum transaction payload
     pub transaction_payload: InputTransactionPayload,
     // An integer that is used to generate derivation_path and distinguish signer accounts
@@ -78,6 +244,52 @@ pub struct InputRequest {
     // Key version that is to be sent to MPC Contract
     // Default is 0
     pub key_version: Option<u32>,
+    // Time (in nanoseconds) during which get_signature() can be called
+    // Must fit into the contract limits, default is one day
+    pub ttl: Option<Duration>,
+    // Chains the transaction can be signed for
+    // Any chain is allowed if not specified
+    pub allowed_chain_ids: Option<Vec<u64>>,
+    // Maximum fee parameters the executor can provide
+    pub gas_fee_limits: Option<GasFeeLimits>,
+    // Maximum fee parameters for specific chains, take precedence over gas_fee_limits
+    pub chain_gas_fee_limits: Option<BTreeMap<u64, GasFeeLimits>>,
+    // How many signatures can be requested, default is Unlimited
+    pub usage_policy: Option<UsagePolicy>,
+    // Type of the transaction to be signed, default is Eip1559
+    pub transaction_type: Option<TransactionType>,
+    // Whether the executor can provide access list in get_signature(), default is true
+    // Ignored if the access list is fixed in transaction_payload
+    pub allow_executor_access_list: Option<bool>,
+    // Whether the executor can provide nonce in get_signature(), default is Disallowed
+    pub nonce_override_policy: Option<NonceOverridePolicy>,
+}
+
+impl InputRequest {
+    // None if neither or both of allowed_executors and allowed_account_id are provided
+    pub fn executors(&self) -> Option<AllowedExecutors> {
+        match (&self.allowed_executors, &self.allowed_account_id) {
+            (Some(allowed_executors), None) => Some(allowed_executors.clone()),
+            (None, Some(account_id)) => Some(AllowedExecutors::Accounts(vec![account_id.clone()])),
+            _ => None,
+        }
+    }
+
+    // Hash that a DAO proposal must include in its description to approve this exact request
+    pub fn commitment(&self) -> String {
+        let serialized =
+            near_sdk::serde_json::to_vec(self).unwrap_or_panic(ContractError::Unexpected);
+
+        Bytes::from(keccak256(serialized)).to_string()
+    }
+}
+
+/// Reference to a Sputnik DAO proposal
+#[derive(Clone)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub struct DaoProposal {
+    pub dao_id: AccountId,
+    pub proposal_id: u64,
 }
 
 /// An internal request wrapped with Eip1559 Transaction Payload
@@ -85,8 +297,14 @@ pub struct InputRequest {
 #[near_sdk::near(serializers = [borsh, json])]
 pub struct Request {
     pub id: RequestId,
-    // The account ID that is authorized to call get_signature()
-    pub allowed_account_id: AccountId,
+    // The account ID that registered the request
+    pub created_by: AccountId,
+    // The time (in nanoseconds) when the request was registered
+    pub created_at: Timestamp,
+    // The block height when the request was registered
+    pub created_at_block: BlockHeight,
+    // Accounts that are authorized to call get_signature()
+    pub allowed_executors: AllowedExecutors,
     // The time limit (in nanoseconds) until get_signature() can be called
     pub deadline: Timestamp,
     // Part of the transaction payload as defined by EIP-1559
@@ -98,6 +316,34 @@ pub struct Request {
     pub derivation_path: String,
     // Key version that is to be sent to MPC Contract
     pub key_version: u32,
+    // How many times get_signature() was called for this request, failed MPC calls aren't counted
+    pub signature_count: u32,
+    // How many produced signatures were recorded to the history, including the dropped ones
+    pub recorded_signature_count: u32,
+    // Deposit paid for the storage occupied by the request
+    // It's refunded to the creator once the request is removed
+    pub storage_deposit: NearToken,
+    // Account that paid the storage deposit, it differs from the creator for DAO approved requests
+    pub storage_payer_id: AccountId,
+    // Deposit paid by the creator for the storage of executors added with add_executor()
+    // It's refunded to the creator, not to the storage payer
+    pub executors_storage_deposit: NearToken,
+    // Chains the transaction can be signed for, any chain if None
+    pub allowed_chain_ids: Option<Vec<u64>>,
+    // Maximum fee parameters the executor can provide
+    pub gas_fee_limits: Option<GasFeeLimits>,
+    // Maximum fee parameters for specific chains, take precedence over gas_fee_limits
+    pub chain_gas_fee_limits: Option<BTreeMap<u64, GasFeeLimits>>,
+    // How many signatures can be requested
+    pub usage_policy: UsagePolicy,
+    // How many times get_signature() was called for each chain
+    pub chain_signature_counts: BTreeMap<u64, u32>,
+    // Type of the transaction to be signed
+    pub transaction_type: TransactionType,
+    // Whether the executor can provide access list in get_signature()
+    pub allow_executor_access_list: bool,
+    // Whether the executor can provide nonce in get_signature()
+    pub nonce_override_policy: NonceOverridePolicy,
 }
 
 impl Request {
@@ -105,55 +351,378 @@ impl Request {
         now > self.deadline
     }
 
+    pub fn status(&self, now: Timestamp) -> RequestStatus {
+        if self.is_time_exceeded(now) {
+            RequestStatus::Expired
+        } else if self.signature_count > 0 {
+            RequestStatus::Used
+        } else {
+            RequestStatus::Active
+        }
+    }
+
     pub fn is_account_allowed(&self, account: AccountId) -> bool {
-        self.allowed_account_id == account
+        self.allowed_executors.contains(&account)
+    }
+
+    pub fn is_chain_allowed(&self, chain_id: u64) -> bool {
+        self.allowed_chain_ids
+            .as_ref()
+            .map_or(true, |chain_ids| chain_ids.contains(&chain_id))
+    }
+
+    pub fn gas_fee_limits_for(&self, chain_id: u64) -> Option<&GasFeeLimits> {
+        self.chain_gas_fee_limits
+            .as_ref()
+            .and_then(|limits| limits.get(&chain_id))
+            .or(self.gas_fee_limits.as_ref())
+    }
+
+    pub fn chain_signature_count(&self, chain_id: u64) -> u32 {
+        self.chain_signature_counts
+            .get(&chain_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Error of the usage policy that doesn't allow one more signature for the chain
+    pub fn usage_error(&self, chain_id: u64) -> Option<ContractError> {
+        match self.usage_policy {
+            UsagePolicy::OncePerChain if self.chain_signature_count(chain_id) > 0 => {
+                Some(ContractError::AlreadySignedForChain)
+            }
+            UsagePolicy::MaxSignatures(max) if self.signature_count >= max => {
+                Some(ContractError::SignatureLimitReached)
+            }
+            // counters of a new chain would grow the request beyond its storage deposit
+            _ if self.chain_signature_count(chain_id) == 0
+                && self.chain_signature_counts.len() >= MAX_SIGNED_CHAINS =>
+            {
+                Some(ContractError::ChainLimitReached)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn assert_access_list_allowed(&self, other_payload: &OtherEip1559TransactionPayload) {
+        if other_payload.access_list.is_none() {
+            return;
+        }
+
+        ensure(
+            self.transaction_type != TransactionType::Legacy,
+            ContractError::AccessListNotSupported,
+        );
+        // access list fixed by the creator can't be replaced
+        ensure(
+            self.allow_executor_access_list && self.payload.access_list.is_none(),
+            ContractError::AccessListNotAllowed,
+        );
+    }
+
+    // Nonce provided by the executor takes precedence over the base one
+    pub fn nonce_for(&self, other_payload: &OtherEip1559TransactionPayload) -> JsonU256 {
+        other_payload.nonce.unwrap_or(self.payload.nonce)
+    }
+
+    pub fn assert_nonce_allowed(&self, other_payload: &OtherEip1559TransactionPayload) {
+        if let Some(nonce) = other_payload.nonce {
+            ensure(
+                self.nonce_override_policy != NonceOverridePolicy::Disallowed,
+                ContractError::NonceOverrideNotAllowed,
+            );
+            ensure(
+                self.nonce_override_policy != NonceOverridePolicy::NotBelowBase
+                    || nonce >= self.payload.nonce,
+                ContractError::NonceBelowBase,
+            );
+        }
+    }
+
+    pub fn assert_usage_allowed(&self, chain_id: u64) {
+        if let Some(error) = self.usage_error(chain_id) {
+            error.panic()
+        }
+    }
+
+    pub fn record_signature(&mut self, chain_id: u64) {
+        self.signature_count += 1;
+        *self.chain_signature_counts.entry(chain_id).or_insert(0) += 1;
+    }
+
+    // Signature that MPC Contract failed to produce doesn't count towards the usage policy
+    pub fn revert_signature(&mut self, chain_id: u64) {
+        self.signature_count = self.signature_count.saturating_sub(1);
+
+        if let Some(count) = self.chain_signature_counts.get_mut(&chain_id) {
+            *count = count.saturating_sub(1);
+
+            if *count == 0 {
+                self.chain_signature_counts.remove(&chain_id);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub enum TransactionType {
+    // Type 0, signed according to EIP-155
+    Legacy,
+    // Type 1
+    Eip2930,
+    // Type 2
+    #[default]
+    Eip1559,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub enum NonceOverridePolicy {
+    // Base nonce is used for every chain
+    #[default]
+    Disallowed,
+    // Executor can provide nonce that isn't lower than the base one
+    NotBelowBase,
+    // Executor can provide any nonce
+    Any,
+}
+
+/// Derived account on a specific chain, used to track the highest signed nonce
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[near_sdk::near(serializers = [borsh])]
+pub struct SignerChainKey {
+    pub derivation_path: String,
+    pub chain_id: u64,
+}
+
+impl SignerChainKey {
+    // Bytes occupied by the entry of highest_signed_nonces
+    pub fn entry_storage_usage(&self) -> StorageUsage {
+        // map prefix, borsh encoded key (length-prefixed path and chain id) and 32-byte nonce
+        let key_len = 1 + 4 + self.derivation_path.len() as u64 + 8;
+
+        STORAGE_RECORD_OVERHEAD + key_len + 32
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub enum UsagePolicy {
+    // Only one signature can be requested for each chain
+    OncePerChain,
+    // No more than N signatures can be requested across all chains
+    MaxSignatures(u32),
+    // Signatures can be requested until the deadline
+    #[default]
+    Unlimited,
+}
+
+impl UsagePolicy {
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, Self::MaxSignatures(0))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub enum AllowedExecutors {
+    // Only the listed accounts can call get_signature()
+    Accounts(Vec<AccountId>),
+    // Any account can call get_signature()
+    Anyone,
+    // Members of the role in Sputnik DAO policy can call get_signature()
+    // Membership is checked with a cross-contract call during get_signature()
+    DaoRole { dao_id: AccountId, role: String },
+}
+
+impl AllowedExecutors {
+    pub fn contains(&self, account: &AccountId) -> bool {
+        match self {
+            Self::Accounts(accounts) => accounts.contains(account),
+            Self::Anyone => true,
+            // can't be checked synchronously
+            Self::DaoRole { .. } => false,
+        }
+    }
+
+    // Accounts that are listed explicitly, empty for other variants
+    pub fn accounts(&self) -> &[AccountId] {
+        match self {
+            Self::Accounts(accounts) => accounts,
+            _ => &[],
+        }
+    }
+
+    pub fn accounts_mut(&mut self) -> Option<&mut Vec<AccountId>> {
+        match self {
+            Self::Accounts(accounts) => Some(accounts),
+            _ => None,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Accounts(accounts) => !accounts.is_empty(),
+            Self::Anyone => true,
+            Self::DaoRole { role, .. } => !role.is_empty(),
+        }
+    }
+
+    pub fn deduplicated(self) -> Self {
+        match self {
+            Self::Accounts(accounts) => {
+                let mut unique_accounts: Vec<AccountId> = Vec::with_capacity(accounts.len());
+
+                for account in accounts {
+                    if !unique_accounts.contains(&account) {
+                        unique_accounts.push(account);
+                    }
+                }
+
+                Self::Accounts(unique_accounts)
+            }
+            other => other,
+        }
     }
 }
 
+#[derive(Clone)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub struct RequestTtlLimits {
+    // Nanoseconds
+    pub min: Duration,
+    // Nanoseconds
+    pub max: Duration,
+}
+
+impl RequestTtlLimits {
+    pub fn is_valid(&self) -> bool {
+        self.min > 0 && self.min <= self.max && self.max <= MAX_REQUEST_TTL_LIMIT
+    }
+
+    pub fn contains(&self, ttl: Duration) -> bool {
+        self.min <= ttl && ttl <= self.max
+    }
+
+    pub fn clamp(&self, ttl: Duration) -> Duration {
+        ttl.clamp(self.min, self.max)
+    }
+}
+
+/// Ceilings for the fee parameters of EVM transaction, not limited if None
+#[derive(Clone, PartialEq, Debug, Default)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub struct GasFeeLimits {
+    pub max_fee_per_gas: Option<JsonU256>,
+    pub max_priority_fee_per_gas: Option<JsonU256>,
+    pub gas: Option<JsonU256>,
+}
+
+impl GasFeeLimits {
+    pub fn assert_allows(&self, payload: &OtherEip1559TransactionPayload) {
+        if let Some(max_fee_per_gas) = self.max_fee_per_gas {
+            ensure(
+                payload.max_fee_per_gas.0 <= max_fee_per_gas.0,
+                ContractError::MaxFeePerGasExceeded,
+            );
+        }
+
+        if let Some(max_priority_fee_per_gas) = self.max_priority_fee_per_gas {
+            ensure(
+                payload.max_priority_fee_per_gas.0 <= max_priority_fee_per_gas.0,
+                ContractError::MaxPriorityFeePerGasExceeded,
+            );
+        }
+
+        if let Some(gas) = self.gas {
+            ensure(
+                payload.gas_or_default() <= gas.0,
+                ContractError::GasExceeded,
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[near_sdk::near(serializers = [json])]
+pub enum RequestStatus {
+    // Deadline hasn't passed and no signature was requested yet
+    Active,
+    // Deadline has passed, get_signature() can't be called anymore
+    Expired,
+    // Deadline hasn't passed and at least one signature was requested
+    Used,
+}
+
 #[derive(Clone)]
 #[near_sdk::near(serializers = [borsh, json])]
 pub struct BaseEip1559TransactionPayload {
-    pub to: String,
+    // None for contract deployment
+    pub to: Option<String>,
     pub data: Option<String>,
-    // TODO: migrate to U256
-    pub value: Option<U128>,
-    // TODO: migrate to U256
-    pub nonce: U128,
+    pub value: Option<JsonU256>,
+    pub nonce: JsonU256,
+    pub access_list: Option<Vec<AccessListEntry>>,
 }
 
 impl From<BaseEip1559TransactionPayload> for Eip1559TransactionRequest {
     fn from(payload: BaseEip1559TransactionPayload) -> Self {
-        let to =
-            NameOrAddress::Address(H160::from_str(&payload.to).expect("ERR_CANT_PARSE_ADDRESS"));
+        let to = payload.to.map(|to| {
+            NameOrAddress::Address(
+                H160::from_str(&to).unwrap_or_panic(ContractError::CantParseAddress),
+            )
+        });
         let nonce = payload.nonce.0;
-        let value = payload.value.unwrap_or(U128(0)).0;
+        let value = payload.value.unwrap_or_default().0;
         let data = Bytes::from_str(payload.data.unwrap_or("0x".to_string()).as_str())
-            .expect("ERR_CANT_PARSE_DATA");
+            .unwrap_or_panic(ContractError::CantParseData);
+        let access_list = to_access_list(payload.access_list.unwrap_or_default());
 
-        Self::new().to(to).nonce(nonce).value(value).data(data)
+        Self { to, ..Self::new() }
+            .nonce(nonce)
+            .value(value)
+            .data(data)
+            .access_list(access_list)
     }
 }
 
 #[near_sdk::near(serializers = [borsh, json])]
 pub struct OtherEip1559TransactionPayload {
     pub chain_id: u64,
-    pub max_fee_per_gas: U128,
-    pub max_priority_fee_per_gas: U128,
-    pub gas: Option<U128>,
+    pub max_fee_per_gas: JsonU256,
+    pub max_priority_fee_per_gas: JsonU256,
+    pub gas: Option<JsonU256>,
+    // Overrides the base nonce if the request allows it
+    pub nonce: Option<JsonU256>,
+    // Only allowed if the request doesn't fix its own access list
+    pub access_list: Option<Vec<AccessListEntry>>,
+}
+
+impl OtherEip1559TransactionPayload {
+    pub fn gas_or_default(&self) -> U256 {
+        self.gas.map_or(U256::from(DEFAULT_TX_GAS), |gas| gas.0)
+    }
 }
 
 impl From<OtherEip1559TransactionPayload> for Eip1559TransactionRequest {
     fn from(payload: OtherEip1559TransactionPayload) -> Self {
         let chain_id = payload.chain_id;
-        let gas = payload.gas.unwrap_or(U128(21_000)).0;
+        let gas = payload.gas_or_default();
         let max_fee_per_gas = payload.max_fee_per_gas.0;
         let max_priority_fee_per_gas = payload.max_priority_fee_per_gas.0;
+        let nonce = payload.nonce.map(|nonce| nonce.0);
+        let access_list = to_access_list(payload.access_list.unwrap_or_default());
 
-        Self::new()
-            .chain_id(chain_id)
-            .gas(gas)
-            .max_fee_per_gas(max_fee_per_gas)
-            .max_priority_fee_per_gas(max_priority_fee_per_gas)
+        Self {
+            nonce,
+            ..Self::new()
+        }
+        .chain_id(chain_id)
+        .gas(gas)
+        .max_fee_per_gas(max_fee_per_gas)
+        .max_priority_fee_per_gas(max_priority_fee_per_gas)
+        .access_list(access_list)
     }
 }
 
@@ -162,15 +731,170 @@ pub struct RegisterSignatureReqResponse {
     pub request_id: RequestId,
     // Nanoseconds
     pub deadline: Timestamp,
+    pub created_by: AccountId,
+    // Nanoseconds
+    pub created_at: Timestamp,
+    pub created_at_block: BlockHeight,
     pub derivation_path: String,
     pub mpc_account_id: AccountId,
-    pub allowed_account_id: AccountId,
+    pub allowed_executors: AllowedExecutors,
+    // Set if there's a single executor, the layout returned up to v0.6.x
+    pub allowed_account_id: Option<AccountId>,
+    // Address that is to be funded before signing
+    // None until MPC public key is cached with refresh_mpc_public_key()
+    pub derived_address: Option<String>,
+}
+
+#[near_sdk::near(serializers = [json])]
+pub struct CleanupExpiredResponse {
+    pub removed: Vec<RequestId>,
+    // Index to continue the cleanup from, None once all requests were examined
+    pub next_index: Option<u64>,
+}
+
+/// Account derived by MPC Contract for the abstract-dao contract and derivation path
+#[near_sdk::near(serializers = [json])]
+pub struct DerivedAccount {
+    pub derivation_path: String,
+    pub public_key: PublicKey,
+    pub address: String,
+}
+
+/// Signature response of MPC Contract
+// https://github.com/near/mpc/blob/develop/chain-signatures/crypto-shared/src/types.rs
+#[derive(Clone, PartialEq, Debug)]
+#[near_sdk::near(serializers = [json])]
+pub struct SignatureResponse {
+    pub big_r: SerializableAffinePoint,
+    pub s: SerializableScalar,
+    pub recovery_id: u8,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[near_sdk::near(serializers = [json])]
+pub struct SerializableAffinePoint {
+    // hex-encoded SEC1 point
+    pub affine_point: String,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[near_sdk::near(serializers = [json])]
+pub struct SerializableScalar {
+    // hex-encoded 32 bytes
+    pub scalar: String,
+}
+
+/// Signature in the form expected by EVM chains
+#[derive(Clone)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub struct EvmSignature {
+    pub r: String,
+    pub s: String,
+    // EIP-155 value for legacy transactions, equal to y_parity for typed transactions
+    pub v: u64,
+    pub y_parity: u8,
 }
 
 #[near_sdk::near(serializers = [json])]
 pub struct GetSignatureResponse {
     pub tx: String,
-    pub signature: near_sdk::serde_json::Value,
+    pub signature: SignatureResponse,
+    pub evm_signature: EvmSignature,
+    // RLP-encoded signed transaction, can be broadcasted with eth_sendRawTransaction
+    pub signed_tx: String,
+    pub tx_hash: String,
+    // Address recovered from the signature
+    pub from: String,
+}
+
+/// Result of get_signature(), failure is returned when MPC Contract call fails or times out
+#[near_sdk::near(serializers = [json])]
+#[serde(untagged)]
+pub enum GetSignatureResult {
+    Signed(GetSignatureResponse),
+    Failed(SignatureFailure),
+}
+
+/// Signature produced for a request, kept so a lost transaction can be relayed again
+#[derive(Clone)]
+#[near_sdk::near(serializers = [borsh, json])]
+pub struct SignatureRecord {
+    pub chain_id: u64,
+    pub tx_hash: String,
+    pub nonce: JsonU256,
+    pub gas: JsonU256,
+    // Gas price for legacy and EIP-2930 transactions
+    pub max_fee_per_gas: JsonU256,
+    // Only set for EIP-1559 transactions
+    pub max_priority_fee_per_gas: Option<JsonU256>,
+    // Access list of the signed transaction, fixed by the request or provided by the executor
+    pub access_list: Option<Vec<AccessListEntry>>,
+    pub signature: EvmSignature,
+    pub executor_id: AccountId,
+    // The time (in nanoseconds) when the signature was produced
+    pub signed_at: Timestamp,
+}
+
+impl SignatureRecord {
+    // Bytes occupied by the entry of signature_history at most, executor id and access list
+    // vary in size
+    pub fn entry_storage_usage(
+        executor_id: &AccountId,
+        access_list: Option<&[AccessListEntry]>,
+    ) -> StorageUsage {
+        // map prefix and borsh encoded key (request id and slot)
+        let key_len = 1 + 8 + 4;
+        // chain id, hex encoded hash, nonce, gas, fees, hex encoded r and s, v, y parity
+        // and signing time
+        let fixed_len = 8 + (4 + 66) + 32 + 32 + 32 + (1 + 32) + 2 * (4 + 66) + 8 + 1 + 8;
+        // option tag and length-prefixed entries of length-prefixed address and storage keys
+        let access_list_len = 1 + access_list.map_or(0, |entries| {
+            4 + entries
+                .iter()
+                .map(|entry| {
+                    4 + entry.address.len() as u64
+                        + 4
+                        + entry
+                            .storage_keys
+                            .iter()
+                            .map(|key| 4 + key.len() as u64)
+                            .sum::<u64>()
+                })
+                .sum::<u64>()
+        });
+
+        STORAGE_RECORD_OVERHEAD
+            + key_len
+            + fixed_len
+            + access_list_len
+            + 4
+            + executor_id.len() as u64
+    }
+
+    // Storage paid by the executor for the record, it's refunded once the record is removed
+    pub fn storage_usage(&self) -> StorageUsage {
+        Self::entry_storage_usage(&self.executor_id, self.access_list.as_deref())
+    }
+}
+
+/// Slot of the request signature history, the oldest record is overwritten once it's full
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[near_sdk::near(serializers = [borsh])]
+pub struct SignatureRecordKey {
+    pub request_id: RequestId,
+    pub slot: u32,
+}
+
+#[derive(Clone)]
+#[near_sdk::near(serializers = [json])]
+pub struct SignatureFailure {
+    pub request_id: RequestId,
+    pub chain_id: u64,
+    pub derivation_path: String,
+    pub executor_id: AccountId,
+    pub error: ContractError,
+    // Deposit attached to get_signature() that is returned to the executor
+    pub refunded_deposit: NearToken,
 }
 
 #[cfg(test)]
@@ -179,28 +903,31 @@ mod tests {
         abi::{Param, ParamType, StateMutability},
         types::{Eip1559TransactionRequest, U256},
     };
-    use near_sdk::json_types::U128;
 
     use super::*;
 
     fn base_payload() -> BaseEip1559TransactionPayload {
         BaseEip1559TransactionPayload {
-            to: "0x0000000000000000000000000000000000000000".to_string(),
-            nonce: U128(0),
-            value: Some(U128(1)),
+            to: Some("0x0000000000000000000000000000000000000000".to_string()),
+            nonce: JsonU256::from(0),
+            value: Some(JsonU256::from(1)),
             data: Some("0x2386f26fc10000".to_string()),
+            access_list: None,
         }
     }
 
     fn other_payload() -> OtherEip1559TransactionPayload {
         OtherEip1559TransactionPayload {
             chain_id: 1111,
-            gas: Some(U128(42_000)),
-            max_fee_per_gas: U128(120_000),
-            max_priority_fee_per_gas: U128(120_000),
+            gas: Some(JsonU256::from(42_000)),
+            max_fee_per_gas: JsonU256::from(120_000),
+            max_priority_fee_per_gas: JsonU256::from(120_000),
+            nonce: None,
+            access_list: None,
         }
     }
 
+    #[allow(deprecated)]
     fn function_data(arguments: Vec<Token>) -> FunctionData {
         FunctionData {
             function_abi: Function {
@@ -214,7 +941,7 @@ mod tests {
                 constant: None,
                 state_mutability: StateMutability::NonPayable,
             },
-            arguments: arguments,
+            arguments,
         }
     }
 
@@ -228,7 +955,7 @@ mod tests {
     #[test]
     fn test_base_payload_into_eip1559_tx_panics_on_empty_address() {
         let mut payload = base_payload();
-        payload.to = "".to_string();
+        payload.to = Some("".to_string());
 
         let _: Eip1559TransactionRequest = payload.into();
     }
@@ -237,7 +964,7 @@ mod tests {
     #[test]
     fn test_base_payload_into_eip1559_tx_panics_on_wrong_address() {
         let mut payload = base_payload();
-        payload.to = "4141ajkl412pp41fakfa".to_string();
+        payload.to = Some("4141ajkl412pp41fakfa".to_string());
 
         let _: Eip1559TransactionRequest = payload.into();
     }
@@ -260,20 +987,22 @@ mod tests {
     #[test]
     fn test_input_transaction_payload_into_base_payload() {
         let input = InputTransactionPayload {
-            to: "0x0000000000000000000000000000000000000000".to_string(),
-            nonce: U128(0),
-            value: Some(U128(1000)),
+            to: Some("0x0000000000000000000000000000000000000000".to_string()),
+            nonce: JsonU256::from(0),
+            value: Some(JsonU256::from(1000)),
             function_data: Some(function_data(vec![Token::Uint(U256([2000, 0, 0, 0]))])),
+            deployment_data: None,
+            access_list: None,
         };
 
         let base_payload: BaseEip1559TransactionPayload = input.into();
 
         assert_eq!(
             base_payload.to,
-            "0x0000000000000000000000000000000000000000".to_string()
+            Some("0x0000000000000000000000000000000000000000".to_string())
         );
-        assert_eq!(base_payload.nonce, U128(0));
-        assert_eq!(base_payload.value, Some(U128(1000)));
+        assert_eq!(base_payload.nonce, JsonU256::from(0));
+        assert_eq!(base_payload.value, Some(JsonU256::from(1000)));
         assert_eq!(
             base_payload.data,
             Some(
@@ -287,26 +1016,242 @@ mod tests {
     #[test]
     fn test_input_transaction_payload_into_base_payload_panics_on_wrong_address() {
         let input = InputTransactionPayload {
-            to: "0x0000000000000000000000000000000000000000?".to_string(),
-            nonce: U128(0),
-            value: Some(U128(1000)),
+            to: Some("0x0000000000000000000000000000000000000000?".to_string()),
+            nonce: JsonU256::from(0),
+            value: Some(JsonU256::from(1000)),
             function_data: None,
+            deployment_data: None,
+            access_list: None,
         };
 
         let _: BaseEip1559TransactionPayload = input.into();
     }
 
-    #[should_panic = "Function arguments don't match provided ABI"]
+    #[should_panic = "ERR_INVALID_FUNCTION_ARGUMENTS"]
     #[test]
     fn test_input_transaction_payload_into_base_payload_panics_on_invalid_function_arguments() {
         let input = InputTransactionPayload {
-            to: "0x0000000000000000000000000000000000000000".to_string(),
-            nonce: U128(0),
-            value: Some(U128(1000)),
+            to: Some("0x0000000000000000000000000000000000000000".to_string()),
+            nonce: JsonU256::from(0),
+            value: Some(JsonU256::from(1000)),
             function_data: Some(function_data(vec![])),
+            deployment_data: None,
+            access_list: None,
         };
 
         // must panic since one argument is expected, but wasn't provided
         let _: BaseEip1559TransactionPayload = input.into();
     }
+
+    #[test]
+    fn test_gas_fee_limits_allow_payload_within_limits() {
+        let limits = GasFeeLimits {
+            max_fee_per_gas: Some(JsonU256::from(120_000)),
+            max_priority_fee_per_gas: None,
+            gas: Some(JsonU256::from(42_000)),
+        };
+
+        limits.assert_allows(&other_payload());
+    }
+
+    #[should_panic = "ERR_MAX_PRIORITY_FEE_PER_GAS_EXCEEDED"]
+    #[test]
+    fn test_gas_fee_limits_panic_on_exceeded_priority_fee() {
+        let limits = GasFeeLimits {
+            max_priority_fee_per_gas: Some(JsonU256::from(100_000)),
+            ..Default::default()
+        };
+
+        limits.assert_allows(&other_payload());
+    }
+
+    #[should_panic = "ERR_GAS_EXCEEDED"]
+    #[test]
+    fn test_gas_fee_limits_apply_to_default_gas() {
+        let limits = GasFeeLimits {
+            gas: Some(JsonU256::from(20_000)),
+            ..Default::default()
+        };
+
+        let mut payload = other_payload();
+        payload.gas = None;
+
+        // 21000 is used when gas isn't provided
+        limits.assert_allows(&payload);
+    }
+
+    fn access_list_entry(address: &str) -> AccessListEntry {
+        AccessListEntry {
+            address: address.to_string(),
+            storage_keys: vec![
+                "0x0000000000000000000000000000000000000000000000000000000000000001".to_string(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_input_transaction_payload_normalizes_access_list() {
+        let input = InputTransactionPayload {
+            to: Some("0x0000000000000000000000000000000000000000".to_string()),
+            nonce: JsonU256::from(0),
+            value: None,
+            function_data: None,
+            deployment_data: None,
+            access_list: Some(vec![access_list_entry(
+                "0xE2A01146FFfC8432497ae49A7a6cBa5B9Abd71A3",
+            )]),
+        };
+
+        let payload: BaseEip1559TransactionPayload = input.into();
+
+        assert_eq!(
+            payload.access_list,
+            Some(vec![access_list_entry(
+                "0xe2a01146fffc8432497ae49a7a6cba5b9abd71a3"
+            )])
+        );
+    }
+
+    #[should_panic = "ERR_CANT_PARSE_ACCESS_LIST"]
+    #[test]
+    fn test_input_transaction_payload_panics_on_wrong_access_list() {
+        let input = InputTransactionPayload {
+            to: Some("0x0000000000000000000000000000000000000000".to_string()),
+            nonce: JsonU256::from(0),
+            value: None,
+            function_data: None,
+            deployment_data: None,
+            access_list: Some(vec![access_list_entry("4141ajkl412pp41fakfa")]),
+        };
+
+        let _: BaseEip1559TransactionPayload = input.into();
+    }
+
+    fn deployment_payload(deployment_data: DeploymentData) -> InputTransactionPayload {
+        InputTransactionPayload {
+            to: None,
+            nonce: JsonU256::from(0),
+            value: None,
+            function_data: None,
+            deployment_data: Some(deployment_data),
+            access_list: None,
+        }
+    }
+
+    #[allow(deprecated)]
+    fn constructor_deployment_data(arguments: Vec<Token>) -> DeploymentData {
+        DeploymentData {
+            bytecode: "0x6080604052".to_string(),
+            constructor_abi: Some(Constructor {
+                inputs: vec![Param {
+                    name: "_num".to_string(),
+                    kind: ParamType::Uint(256),
+                    internal_type: Some("uint256".to_string()),
+                }],
+            }),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn test_deployment_payload_into_base_payload() {
+        let input = deployment_payload(constructor_deployment_data(vec![Token::Uint(U256([
+            2000, 0, 0, 0,
+        ]))]));
+
+        let base_payload: BaseEip1559TransactionPayload = input.into();
+
+        assert_eq!(base_payload.to, None);
+        assert_eq!(
+            base_payload.data,
+            Some(
+                "0x608060405200000000000000000000000000000000000000000000000000000000000007d0"
+                    .to_string()
+            )
+        );
+
+        let tx: Eip1559TransactionRequest = base_payload.into();
+        assert_eq!(tx.to, None);
+    }
+
+    #[test]
+    fn test_deployment_payload_without_constructor_into_base_payload() {
+        let input = deployment_payload(DeploymentData {
+            bytecode: "0x6080604052".to_string(),
+            constructor_abi: None,
+            arguments: vec![],
+        });
+
+        let base_payload: BaseEip1559TransactionPayload = input.into();
+
+        assert_eq!(base_payload.data, Some("0x6080604052".to_string()));
+    }
+
+    #[should_panic = "ERR_INVALID_CONSTRUCTOR_ARGUMENTS"]
+    #[test]
+    fn test_deployment_payload_panics_on_invalid_constructor_arguments() {
+        let input = deployment_payload(constructor_deployment_data(vec![]));
+
+        let _: BaseEip1559TransactionPayload = input.into();
+    }
+
+    #[should_panic = "ERR_INVALID_DEPLOYMENT"]
+    #[test]
+    fn test_deployment_payload_panics_on_receiver_address() {
+        let mut input = deployment_payload(constructor_deployment_data(vec![]));
+        input.to = Some("0x0000000000000000000000000000000000000000".to_string());
+
+        let _: BaseEip1559TransactionPayload = input.into();
+    }
+
+    #[should_panic = "ERR_INVALID_DEPLOYMENT"]
+    #[test]
+    fn test_input_transaction_payload_panics_without_receiver_address() {
+        let mut input = deployment_payload(constructor_deployment_data(vec![]));
+        input.deployment_data = None;
+
+        let _: BaseEip1559TransactionPayload = input.into();
+    }
+
+    #[test]
+    fn test_json_u256_deserializes_decimal_and_hex() {
+        let decimal: JsonU256 = near_sdk::serde_json::from_str(
+            "\"115792089237316195423570985008687907853269984665640564039457584007913129639935\"",
+        )
+        .unwrap();
+        let hex: JsonU256 = near_sdk::serde_json::from_str(
+            "\"0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\"",
+        )
+        .unwrap();
+
+        assert_eq!(decimal, JsonU256(U256::MAX));
+        assert_eq!(hex, JsonU256(U256::MAX));
+    }
+
+    #[test]
+    fn test_json_u256_serializes_as_decimal_string() {
+        let value = JsonU256(U256::from(u128::MAX) + 1);
+
+        assert_eq!(
+            near_sdk::serde_json::to_string(&value).unwrap(),
+            "\"340282366920938463463374607431768211456\""
+        );
+    }
+
+    #[test]
+    fn test_json_u256_rejects_invalid_string() {
+        assert!(near_sdk::serde_json::from_str::<JsonU256>("\"0xzz\"").is_err());
+        assert!(near_sdk::serde_json::from_str::<JsonU256>("\"-1\"").is_err());
+        assert!(near_sdk::serde_json::from_str::<JsonU256>("1").is_err());
+    }
+
+    #[test]
+    fn test_json_u256_borsh_roundtrip() {
+        let value = JsonU256(U256::MAX - 5);
+
+        let bytes = near_sdk::borsh::to_vec(&value).unwrap();
+
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(JsonU256::try_from_slice(&bytes).unwrap(), value);
+    }
 }
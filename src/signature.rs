@@ -0,0 +1,294 @@
+use ethers_core::k256::elliptic_curve::scalar::IsHigh;
+use ethers_core::k256::elliptic_curve::PrimeField;
+use ethers_core::k256::{FieldBytes, Scalar};
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{Bytes, Signature, H160, H256, U256};
+use ethers_core::utils::hex;
+use near_sdk::env;
+
+use crate::errors::ContractError;
+use crate::primitives::SignatureResponse;
+
+const SECP256K1_FIELD_MODULUS: &str =
+    "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F";
+
+/// Validated signature produced by MPC Contract
+pub struct MpcSignature {
+    pub r: U256,
+    pub s: U256,
+    pub recovery_id: u8,
+}
+
+impl TryFrom<&SignatureResponse> for MpcSignature {
+    type Error = ContractError;
+
+    fn try_from(response: &SignatureResponse) -> Result<Self, Self::Error> {
+        // big_r isn't decompressed, since it's too expensive in wasm. A x coordinate that isn't
+        // on the curve makes the sender recovery fail instead
+        let big_r_x = hex::decode(&response.big_r.affine_point)
+            .ok()
+            .filter(|big_r| big_r.len() == 33 && matches!(big_r[0], 0x02 | 0x03))
+            .map(|big_r| U256::from_big_endian(&big_r[1..]))
+            .filter(|x| !x.is_zero() && *x < secp256k1_field_modulus())
+            .ok_or(ContractError::InvalidBigR)?;
+
+        let s = hex::decode(&response.s.scalar)
+            .ok()
+            .filter(|s| s.len() == 32)
+            .and_then(|s| Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(&s))))
+            .filter(|s| !bool::from(s.is_zero()))
+            .ok_or(ContractError::InvalidS)?;
+        if bool::from(s.is_high()) {
+            return Err(ContractError::HighS);
+        }
+
+        if response.recovery_id > 1 {
+            return Err(ContractError::InvalidRecoveryId);
+        }
+
+        Ok(Self {
+            // r is the x coordinate of big_r
+            r: big_r_x,
+            s: U256::from_big_endian(&s.to_bytes()),
+            recovery_id: response.recovery_id,
+        })
+    }
+}
+
+impl MpcSignature {
+    pub fn to_evm_signature(&self, tx: &TypedTransaction) -> Signature {
+        let v = match tx {
+            // https://eips.ethereum.org/EIPS/eip-155
+            TypedTransaction::Legacy(_) => match tx.chain_id() {
+                Some(chain_id) => self.recovery_id as u64 + 35 + chain_id.as_u64() * 2,
+                None => self.recovery_id as u64 + 27,
+            },
+            // typed transactions use y parity directly
+            _ => self.recovery_id as u64,
+        };
+
+        Signature {
+            r: self.r,
+            s: self.s,
+            v,
+        }
+    }
+}
+
+fn secp256k1_field_modulus() -> U256 {
+    U256::from_big_endian(&hex::decode(SECP256K1_FIELD_MODULUS).unwrap_or_default())
+}
+
+// 32-byte big-endian representation used for r and s
+pub fn to_bytes32(value: U256) -> Bytes {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+
+    Bytes::from(bytes.to_vec())
+}
+
+pub struct SignedTransaction {
+    pub raw: Bytes,
+    pub hash: H256,
+    pub from: H160,
+    pub signature: Signature,
+}
+
+pub fn sign_tx(
+    tx: &TypedTransaction,
+    mpc_signature: &MpcSignature,
+) -> Result<SignedTransaction, ContractError> {
+    let signature = mpc_signature.to_evm_signature(tx);
+
+    let raw = tx.rlp_signed(&signature);
+    let sighash = env::keccak256_array(&tx.rlp());
+    let from = recover_signer(&sighash, mpc_signature).ok_or(ContractError::CantRecoverSigner)?;
+
+    Ok(SignedTransaction {
+        hash: H256::from(env::keccak256_array(&raw)),
+        raw,
+        from,
+        signature,
+    })
+}
+
+// Recovery is done by the host function, doing it in wasm doesn't fit into the callback gas
+fn recover_signer(sighash: &[u8; 32], mpc_signature: &MpcSignature) -> Option<H160> {
+    let mut signature = [0u8; 64];
+    mpc_signature.r.to_big_endian(&mut signature[..32]);
+    mpc_signature.s.to_big_endian(&mut signature[32..]);
+
+    let public_key = env::ecrecover(sighash, &signature, mpc_signature.recovery_id, true)?;
+
+    // address is the last 20 bytes of the public key hash
+    Some(H160::from_slice(&env::keccak256_array(&public_key)[12..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers_core::k256::ecdsa::SigningKey;
+    use ethers_core::types::{Eip1559TransactionRequest, TransactionRequest, U64};
+    use ethers_core::utils::keccak256;
+    use ethers_core::utils::rlp::Rlp;
+
+    use crate::primitives::{SerializableAffinePoint, SerializableScalar};
+
+    use super::*;
+
+    // Signs the same way MPC Contract does and returns its response
+    fn mpc_sign(private_key: [u8; 32], tx: &TypedTransaction) -> SignatureResponse {
+        let signing_key = SigningKey::from_bytes(&private_key.into()).unwrap();
+        let (signature, recovery_id) = signing_key
+            .sign_prehash_recoverable(tx.sighash().as_bytes())
+            .unwrap();
+
+        let mut big_r = vec![if recovery_id.is_y_odd() { 0x03 } else { 0x02 }];
+        big_r.extend_from_slice(&signature.r().to_bytes());
+
+        signature_response(
+            &hex::encode_upper(big_r),
+            &hex::encode_upper(signature.s().to_bytes()),
+            recovery_id.to_byte(),
+        )
+    }
+
+    fn signature_response(big_r: &str, s: &str, recovery_id: u8) -> SignatureResponse {
+        SignatureResponse {
+            big_r: SerializableAffinePoint {
+                affine_point: big_r.to_string(),
+            },
+            s: SerializableScalar {
+                scalar: s.to_string(),
+            },
+            recovery_id,
+        }
+    }
+
+    fn eip155_example_tx() -> TypedTransaction {
+        TransactionRequest::new()
+            .nonce(9)
+            .gas_price(20_000_000_000u64)
+            .gas(21_000)
+            .to(H160::from_str("0x3535353535353535353535353535353535353535").unwrap())
+            .value(1_000_000_000_000_000_000u64)
+            .chain_id(1)
+            .into()
+    }
+
+    #[test]
+    fn test_sign_legacy_tx() {
+        // signed transaction example from EIP-155
+        let tx = eip155_example_tx();
+        let mpc_signature = MpcSignature::try_from(&mpc_sign([0x46; 32], &tx)).unwrap();
+
+        let signed_tx = sign_tx(&tx, &mpc_signature).unwrap();
+
+        assert_eq!(signed_tx.signature.v, 37);
+        assert_eq!(
+            signed_tx.raw.to_string(),
+            "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(
+            signed_tx.from,
+            H160::from_str("0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F").unwrap()
+        );
+        assert_eq!(signed_tx.hash, H256::from(keccak256(&signed_tx.raw)));
+    }
+
+    #[test]
+    fn test_sign_eip1559_tx() {
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .nonce(9)
+            .gas(21_000)
+            .max_fee_per_gas(20_000_000_000u64)
+            .max_priority_fee_per_gas(1_000_000_000u64)
+            .to(H160::from_str("0x3535353535353535353535353535353535353535").unwrap())
+            .chain_id(11_155_111)
+            .into();
+        let mpc_signature = MpcSignature::try_from(&mpc_sign([0x46; 32], &tx)).unwrap();
+
+        let signed_tx = sign_tx(&tx, &mpc_signature).unwrap();
+
+        assert_eq!(signed_tx.signature.v, mpc_signature.recovery_id as u64);
+        assert_eq!(signed_tx.raw[0], 2u8);
+        assert_eq!(
+            signed_tx.from,
+            H160::from_str("0x9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F").unwrap()
+        );
+
+        let (decoded_tx, decoded_signature) =
+            TypedTransaction::decode_signed(&Rlp::new(&signed_tx.raw)).unwrap();
+        assert_eq!(decoded_tx.chain_id(), Some(U64::from(11_155_111)));
+        assert_eq!(decoded_signature.r, mpc_signature.r);
+        assert_eq!(decoded_signature.s, mpc_signature.s);
+    }
+
+    #[test]
+    fn test_sign_tx_fails_on_big_r_off_the_curve() {
+        // x = 5 isn't on secp256k1 curve
+        let big_r = format!("02{:064X}", 5);
+        let mpc_signature = MpcSignature::try_from(&signature_response(
+            &big_r,
+            "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83",
+            0,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            sign_tx(&eip155_example_tx(), &mpc_signature).map(|_| ()),
+            Err(ContractError::CantRecoverSigner)
+        );
+    }
+
+    #[test]
+    fn test_signature_validation() {
+        let valid_big_r = "0228EF61340BD939BC2195FE537567866003E1A15D3C71FF63E1590620AA636276";
+        let valid_s = "67CBE9D8997F761AECB703304B3800CCF555C9F3DC64214B297FB1966A3B6D83";
+        let validate = |big_r: &str, s: &str, recovery_id: u8| {
+            MpcSignature::try_from(&signature_response(big_r, s, recovery_id)).map(|_| ())
+        };
+
+        assert_eq!(validate(valid_big_r, valid_s, 0), Ok(()));
+        // zero x coordinate
+        assert_eq!(
+            validate(&format!("02{}", "00".repeat(32)), valid_s, 0),
+            Err(ContractError::InvalidBigR)
+        );
+        // field modulus
+        assert_eq!(
+            validate(&format!("02{SECP256K1_FIELD_MODULUS}"), valid_s, 0),
+            Err(ContractError::InvalidBigR)
+        );
+        assert_eq!(
+            validate(&format!("04{}", &valid_big_r[2..]), valid_s, 0),
+            Err(ContractError::InvalidBigR)
+        );
+        assert_eq!(
+            validate("not a hex", valid_s, 0),
+            Err(ContractError::InvalidBigR)
+        );
+        assert_eq!(
+            validate(valid_big_r, &"00".repeat(32), 0),
+            Err(ContractError::InvalidS)
+        );
+        assert_eq!(
+            validate(valid_big_r, &"FF".repeat(32), 0),
+            Err(ContractError::InvalidS)
+        );
+        // curve order minus 1
+        assert_eq!(
+            validate(
+                valid_big_r,
+                "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140",
+                0
+            ),
+            Err(ContractError::HighS)
+        );
+        assert_eq!(
+            validate(valid_big_r, valid_s, 2),
+            Err(ContractError::InvalidRecoveryId)
+        );
+    }
+}
@@ -60,12 +60,9 @@ pub async fn deploy_mpc_contract(root: &Account) -> Contract {
     // MPC contract is slightly modified!
     // Removed signature check inside fn respond() to be able to respond with a mock
     // as we don't care about signature validity during those tests
-    let wasm = std::fs::read(MPC_CONTRACT_WASM_FILE_PATH).expect(
-        format!(
-            "Couldn't find Wasm file intended for Mpc contract at {MPC_CONTRACT_WASM_FILE_PATH}"
-        )
-        .as_str(),
-    );
+    let wasm = std::fs::read(MPC_CONTRACT_WASM_FILE_PATH).unwrap_or_else(|_| {
+        panic!("Couldn't find Wasm file intended for Mpc contract at {MPC_CONTRACT_WASM_FILE_PATH}")
+    });
 
     let account = root
         .create_subaccount("mpc")
@@ -132,7 +129,9 @@ pub async fn create_signature_request(user: &Account, contract_id: &AccountId) -
         .deposit(NearToken::from_millinear(50)) // 0.05 NEAR
         .args_json(json!({
             "request": {
-                "allowed_account_id": user.id().to_string(),
+                "allowed_executors": {
+                    "Accounts": [user.id().to_string()]
+                },
                 "derivation_seed_number": 0,
                 "transaction_payload": {
                     "to": "0xe2a01146FFfC8432497ae49A7a6cBa5B9Abd71A3",
@@ -249,6 +248,8 @@ pub async fn get_signature_and_validate(
     let response = get_signature_result.json::<Value>().unwrap();
 
     assert!(response["tx"].is_string());
+    assert!(response["signed_tx"].is_string());
+    assert!(response["tx_hash"].is_string());
     assert_eq!(
         response["signature"]["big_r"]["affine_point"],
         "03214BB5B327CEC619FB0447C84E23E5DF462FD758D46F0A21A36EF9BC083EF53B"
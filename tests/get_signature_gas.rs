@@ -0,0 +1,49 @@
+pub mod common;
+use common::{
+    create_signature_request, deploy_abstract_dao_contract, deploy_mpc_contract, SEPOLIA_CHAIN_ID,
+};
+use near_sdk::serde_json::json;
+use near_workspaces::types::{Gas, NearToken};
+
+#[tokio::test]
+async fn test_get_signature_fails_without_gas_for_mpc_sign() {
+    let worker = near_workspaces::sandbox().await.unwrap();
+    let root = worker.root_account().unwrap();
+
+    let mpc_contract = deploy_mpc_contract(&root).await;
+
+    let contract = deploy_abstract_dao_contract(&root, mpc_contract.id()).await;
+
+    let user = root
+        .create_subaccount("user")
+        .initial_balance(NearToken::from_near(5))
+        .transact()
+        .await
+        .unwrap()
+        .unwrap();
+
+    let request_id = create_signature_request(&user, contract.id()).await;
+
+    // 270TGas for signing and 15TGas for the deposit query, as it isn't configured
+    let get_signature_result = user
+        .call(contract.id(), "get_signature")
+        .args_json(json!({
+            "request_id": request_id,
+            "other_payload": {
+                "chain_id": SEPOLIA_CHAIN_ID,
+                "max_fee_per_gas": "111551114121",
+                "max_priority_fee_per_gas": "294111551111"
+            }
+        }))
+        .deposit(NearToken::from_millinear(50)) // 0.05 NEAR
+        .gas(Gas::from_tgas(284))
+        .transact()
+        .await
+        .unwrap();
+
+    assert!(
+        format!("{:?}", get_signature_result.into_result().unwrap_err())
+            .contains("ERR_INSUFFICIENT_GAS"),
+        "Function call get_signature must fail with ERR_INSUFFICIENT_GAS!"
+    );
+}